@@ -1,3 +1,57 @@
+/// Every command of `generate_handler!` in lib.rs. Each one gets an
+/// `allow-<command>` permission, and only the capabilities listing it may call
+/// it: see `permissions/` and `capabilities/`.
+const COMMANDS: &[&str] = &[
+    "list_accounts",
+    "add_account",
+    "remove_account",
+    "switch_account",
+    "set_account_layout",
+    "report_unread",
+    "unread_counts",
+    "toggle_call_always_on_top",
+    "toggle_call_pip",
+    "list_downloads",
+    "get_download_folder",
+    "choose_download_folder",
+    "open_download",
+    "reveal_download",
+    "clear_downloads",
+    "open_media_viewer",
+    "media_viewer_gallery",
+    "save_media",
+    "copy_media",
+    "set_media_permission",
+    "pop_out_conversation",
+    "show_conversation_menu",
+    "pick_share_source",
+    "share_picker_sources",
+    "choose_share_source",
+    "start_screen_share",
+    "screen_share_frame",
+    "stop_screen_share",
+    "get_settings",
+    "update_settings",
+    "toggle_theme",
+    "restart_app",
+    "is_muted",
+    "hide_window",
+    "switcher_ranking",
+    "record_switcher_pick",
+    "check_for_updates",
+    "get_window_zoom",
+    "zoom",
+    "set_font_scale",
+    "window_controls_layout",
+    "show_window_menu",
+    "place_maximize_button",
+    "reset_window_state",
+];
+
 fn main() {
-    tauri_build::build()
+    tauri_build::try_build(
+        tauri_build::Attributes::new()
+            .app_manifest(tauri_build::AppManifest::new().commands(COMMANDS)),
+    )
+    .expect("failed to run tauri-build")
 }
@@ -0,0 +1,1019 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use tauri::{
+    webview::{NewWindowFeatures, NewWindowResponse},
+    AppHandle, Emitter, LogicalPosition, Manager, Runtime, Url, Webview, WebviewBuilder,
+    WebviewUrl, WebviewWindow, WebviewWindowBuilder, Window, WindowBuilder, WindowEvent,
+};
+
+use crate::safe_mode::SafeMode;
+use crate::url_policy::PopupKind;
+use crate::{
+    calls, cli, downloads, injection, media_viewer, permissions, safe_mode, settings, url_policy,
+    user_content, zoom,
+};
+
+static WINDOW_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// Id of the account that keeps the `main` window and the default webview
+/// profile, so sessions from before multi-account support keep working.
+pub const DEFAULT_ACCOUNT_ID: &str = "default";
+
+const ACCOUNTS_FILE: &str = "accounts.json";
+
+/// Buttons of the dialog confirming that an account should be removed.
+const REMOVE: &str = "Remove";
+const CANCEL: &str = "Cancel";
+
+/// wry's default WebView2 arguments, plus Edge's overlay scrollbars.
+#[cfg(windows)]
+const OVERLAY_SCROLLBAR_ARGS: &str = "--disable-features=msWebOOUI,msPdfOOUI,msSmartScreenProtection \
+    --enable-features=OverlayScrollbar,msOverlayScrollbarWinStyle,msOverlayScrollbarWinStyleAnimation";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Account {
+    pub id: String,
+    pub name: String,
+}
+
+/// How multiple accounts are presented.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccountLayout {
+    /// One window per account.
+    #[default]
+    Windows,
+    /// One child webview per account inside the `main` window.
+    Tabs,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountsConfig {
+    #[serde(default)]
+    pub layout: AccountLayout,
+    pub accounts: Vec<Account>,
+    /// Ids of removed accounts whose profile was still in use, deleted on the
+    /// next start before any webview opens it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pending_removal: Vec<String>,
+}
+
+impl Default for AccountsConfig {
+    fn default() -> Self {
+        Self {
+            layout: AccountLayout::default(),
+            accounts: vec![Account {
+                id: DEFAULT_ACCOUNT_ID.into(),
+                name: "Messenger".into(),
+            }],
+            pending_removal: Vec::new(),
+        }
+    }
+}
+
+/// Keeps track of the configured accounts and persists them in the app config dir.
+pub struct AccountManager {
+    config: Mutex<AccountsConfig>,
+    path: PathBuf,
+    /// Id of the account whose tab is visible in the tabbed layout.
+    active: Mutex<String>,
+    /// Last unread count reported by each account's webview.
+    unread: Mutex<HashMap<String, u32>>,
+    /// Set when there is no custom titlebar to host the tab strip.
+    force_windows: bool,
+}
+
+impl AccountManager {
+    pub fn load<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<Self> {
+        let path = app.path().app_config_dir()?.join(ACCOUNTS_FILE);
+
+        let mut config = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str::<AccountsConfig>(&raw).ok())
+            .unwrap_or_default();
+
+        if !config.accounts.iter().any(|a| a.id == DEFAULT_ACCOUNT_ID) {
+            config
+                .accounts
+                .insert(0, AccountsConfig::default().accounts.remove(0));
+        }
+
+        let manager = Self {
+            config: Mutex::new(config),
+            path,
+            active: Mutex::new(DEFAULT_ACCOUNT_ID.into()),
+            unread: Mutex::new(HashMap::new()),
+            force_windows: is_safe_mode(app) || !settings::current(app).features.titlebar,
+        };
+        manager.remove_pending_profiles(app);
+        Ok(manager)
+    }
+
+    /// Deletes the profiles left behind by `schedule_removal`.
+    fn remove_pending_profiles<R: Runtime>(&self, app: &AppHandle<R>) {
+        let mut config = self.config.lock().unwrap();
+        if config.pending_removal.is_empty() {
+            return;
+        }
+
+        let pending = std::mem::take(&mut config.pending_removal);
+        config.pending_removal = pending
+            .into_iter()
+            .filter(|id| !remove_profile(app, id))
+            .collect();
+        self.save(&config);
+    }
+
+    /// Remembers to delete the profile of a removed account on the next start.
+    fn schedule_removal(&self, id: &str) {
+        let mut config = self.config.lock().unwrap();
+        if !config.pending_removal.iter().any(|pending| pending == id) {
+            config.pending_removal.push(id.to_string());
+            self.save(&config);
+        }
+    }
+
+    pub fn accounts(&self) -> Vec<Account> {
+        self.config.lock().unwrap().accounts.clone()
+    }
+
+    /// Layout in use for this launch. Safe mode and a disabled titlebar leave
+    /// no tab strip to switch accounts with, so they use separate windows.
+    pub fn layout(&self) -> AccountLayout {
+        if self.force_windows {
+            AccountLayout::Windows
+        } else {
+            self.config.lock().unwrap().layout
+        }
+    }
+
+    pub fn set_layout(&self, layout: AccountLayout) {
+        let mut config = self.config.lock().unwrap();
+        config.layout = layout;
+        self.save(&config);
+    }
+
+    pub fn get(&self, id: &str) -> Option<Account> {
+        self.config
+            .lock()
+            .unwrap()
+            .accounts
+            .iter()
+            .find(|a| a.id == id)
+            .cloned()
+    }
+
+    fn save(&self, config: &AccountsConfig) {
+        if let Some(dir) = self.path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        match serde_json::to_string_pretty(config) {
+            Ok(raw) => {
+                if let Err(e) = std::fs::write(&self.path, raw) {
+                    log::error!("Failed to save accounts: {}", e);
+                }
+            }
+            Err(e) => log::error!("Failed to serialize accounts: {}", e),
+        }
+    }
+
+    pub fn add(&self, name: &str) -> Account {
+        let mut config = self.config.lock().unwrap();
+
+        let base = slugify(name);
+        let mut id = base.clone();
+        let mut suffix = 2;
+        // A profile waiting to be deleted can't be handed to a new account
+        while id == DEFAULT_ACCOUNT_ID
+            || config.accounts.iter().any(|a| a.id == id)
+            || config.pending_removal.contains(&id)
+        {
+            id = format!("{}-{}", base, suffix);
+            suffix += 1;
+        }
+
+        let account = Account {
+            id,
+            name: name.trim().to_string(),
+        };
+        config.accounts.push(account.clone());
+        self.save(&config);
+        account
+    }
+
+    pub fn remove(&self, id: &str) -> bool {
+        if id == DEFAULT_ACCOUNT_ID {
+            return false;
+        }
+
+        let mut config = self.config.lock().unwrap();
+        let before = config.accounts.len();
+        config.accounts.retain(|a| a.id != id);
+        let removed = config.accounts.len() != before;
+        if removed {
+            self.save(&config);
+        }
+        removed
+    }
+}
+
+fn slugify(name: &str) -> String {
+    let slug: String = name
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let slug = slug.trim_matches('-').to_string();
+    if slug.is_empty() {
+        "account".into()
+    } else {
+        slug
+    }
+}
+
+/// Window label for an account. The default account keeps the `main` label.
+pub fn window_label(account_id: &str) -> String {
+    if account_id == DEFAULT_ACCOUNT_ID {
+        "main".into()
+    } else {
+        format!("account-{}", account_id)
+    }
+}
+
+/// Directory holding the isolated webview profile (cookies, storage) of an account.
+fn profile_dir<R: Runtime>(app: &AppHandle<R>, account_id: &str) -> tauri::Result<PathBuf> {
+    Ok(app
+        .path()
+        .app_local_data_dir()?
+        .join("profiles")
+        .join(account_id))
+}
+
+/// Deletes the profile of an account. Returns false when it's still there,
+/// usually because a webview process hasn't let go of its files yet.
+fn remove_profile<R: Runtime>(app: &AppHandle<R>, account_id: &str) -> bool {
+    let Ok(dir) = profile_dir(app, account_id) else {
+        return false;
+    };
+    match std::fs::remove_dir_all(&dir) {
+        Ok(()) => true,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => true,
+        Err(e) => {
+            log::warn!("Could not remove profile for {}: {}", account_id, e);
+            false
+        }
+    }
+}
+
+/// Stable WKWebView data store id derived from the account id, since macOS
+/// has no per-webview data directory.
+#[cfg(target_os = "macos")]
+fn data_store_identifier(account_id: &str) -> [u8; 16] {
+    let mut id = [0u8; 16];
+    for (i, b) in account_id.bytes().enumerate() {
+        id[i % 16] = id[i % 16].wrapping_mul(31).wrapping_add(b);
+    }
+    id
+}
+
+/// Builder methods choosing the webview profile, shared by windows and tabs.
+trait ProfileBuilder: Sized {
+    fn data_directory(self, data_directory: PathBuf) -> Self;
+    #[cfg(target_os = "macos")]
+    fn data_store_identifier(self, data_store_identifier: [u8; 16]) -> Self;
+    #[cfg(windows)]
+    fn additional_browser_args(self, additional_args: &str) -> Self;
+}
+
+impl<R: Runtime, M: Manager<R>> ProfileBuilder for WebviewWindowBuilder<'_, R, M> {
+    fn data_directory(self, data_directory: PathBuf) -> Self {
+        WebviewWindowBuilder::data_directory(self, data_directory)
+    }
+
+    #[cfg(target_os = "macos")]
+    fn data_store_identifier(self, data_store_identifier: [u8; 16]) -> Self {
+        WebviewWindowBuilder::data_store_identifier(self, data_store_identifier)
+    }
+
+    #[cfg(windows)]
+    fn additional_browser_args(self, additional_args: &str) -> Self {
+        WebviewWindowBuilder::additional_browser_args(self, additional_args)
+    }
+}
+
+impl<R: Runtime> ProfileBuilder for WebviewBuilder<R> {
+    fn data_directory(self, data_directory: PathBuf) -> Self {
+        WebviewBuilder::data_directory(self, data_directory)
+    }
+
+    #[cfg(target_os = "macos")]
+    fn data_store_identifier(self, data_store_identifier: [u8; 16]) -> Self {
+        WebviewBuilder::data_store_identifier(self, data_store_identifier)
+    }
+
+    #[cfg(windows)]
+    fn additional_browser_args(self, additional_args: &str) -> Self {
+        WebviewBuilder::additional_browser_args(self, additional_args)
+    }
+}
+
+/// Opens `builder` in the profile of `account`. WebView2 only shares a
+/// profile between webviews created with the same arguments, so every webview
+/// of an account gets the same ones.
+fn with_account_profile<R: Runtime, B: ProfileBuilder>(
+    mut builder: B,
+    app: &AppHandle<R>,
+    account: &Account,
+) -> tauri::Result<B> {
+    if account.id != DEFAULT_ACCOUNT_ID {
+        builder = builder.data_directory(profile_dir(app, &account.id)?);
+
+        #[cfg(target_os = "macos")]
+        {
+            builder = builder.data_store_identifier(data_store_identifier(&account.id));
+        }
+    }
+
+    #[cfg(windows)]
+    if settings::current(app).scrollbars.overlay {
+        builder = builder.additional_browser_args(OVERLAY_SCROLLBAR_ARGS);
+    }
+
+    Ok(builder)
+}
+
+/// Handles `window.open` requests from a Messenger webview.
+fn popup_handler<R: Runtime>(
+    app: AppHandle<R>,
+) -> impl Fn(Url, NewWindowFeatures) -> NewWindowResponse<R> + Send + Sync + 'static {
+    move |url, features| {
+        // Allow Messenger call windows and other legitimate popups
+        let url_str = url.as_str();
+        log::info!("New window request for URL: {}", url_str);
+
+        // Messenger/Facebook related URLs (calls, auth, etc.) open in the app
+        if url_policy::allows_popup(&url) {
+            let settings = settings::current(&app);
+
+            // Photos and videos go to the media viewer instead of a webview of the CDN
+            if settings.features.media && url_policy::is_media_file(&url) {
+                let gallery = media_viewer::Gallery {
+                    items: vec![media_viewer::media_item(&url)],
+                    index: 0,
+                };
+                if let Err(e) = media_viewer::show(&app, gallery) {
+                    log::error!("Failed to open the media viewer: {}", e);
+                }
+                return NewWindowResponse::Deny;
+            }
+
+            // One label per kind so its size and position are remembered. Other
+            // popups, and a second one of the same kind, get a unique label.
+            let kind = url_policy::popup_kind(&url);
+            let label = match kind {
+                PopupKind::Other => None,
+                kind => Some(kind.label().to_string()),
+            }
+            .filter(|label| app.get_webview_window(label).is_none())
+            .unwrap_or_else(|| {
+                let window_id = WINDOW_COUNTER.fetch_add(1, Ordering::SeqCst);
+                format!("{}-{}", kind.label(), window_id)
+            });
+
+            // Create the popup window for calls/auth. The window features carry
+            // the opener's webview environment, so popups share its profile.
+            let mut builder =
+                WebviewWindowBuilder::new(&app, &label, WebviewUrl::External(url.clone()))
+                    .window_features(features)
+                    .title("Messenger")
+                    .inner_size(800.0, 600.0)
+                    .center()
+                    .devtools(cli::devtools_enabled(&app))
+                    .on_document_title_changed(|window, title| {
+                        let _ = window.set_title(&title);
+                    })
+                    .on_download(downloads::on_download);
+
+            // Calls get the share picker, and their own titlebar with the
+            // call controls
+            if kind == PopupKind::Call && !is_safe_mode(&app) {
+                if settings.features.titlebar {
+                    builder = builder.decorations(false).min_inner_size(240.0, 150.0);
+                }
+                builder = builder.initialization_script(injection::call_script(&settings));
+            }
+
+            match builder.build() {
+                Ok(window) => {
+                    if kind == PopupKind::Call {
+                        calls::on_created(&window);
+                    }
+                    downloads::attach(window.as_ref());
+                    if !is_safe_mode(&app) {
+                        permissions::attach(window.as_ref());
+                    }
+                    NewWindowResponse::Create { window }
+                }
+                Err(e) => {
+                    log::error!("Failed to create popup window: {}", e);
+                    NewWindowResponse::Deny
+                }
+            }
+        } else {
+            // For non-Messenger URLs, open in external browser
+            log::info!("Denying external URL: {}", url_str);
+            NewWindowResponse::Deny
+        }
+    }
+}
+
+fn messenger_url() -> WebviewUrl {
+    WebviewUrl::External("https://www.messenger.com/login".parse().unwrap())
+}
+
+/// Shows the window of an account, creating it if it isn't open yet.
+pub fn open_account_window<R: Runtime>(
+    app: &AppHandle<R>,
+    account: &Account,
+) -> tauri::Result<WebviewWindow<R>> {
+    let label = window_label(&account.id);
+
+    if let Some(window) = app.get_webview_window(&label) {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+        set_active(app, &account.id);
+        return Ok(window);
+    }
+
+    let safe_mode = is_safe_mode(app);
+    let settings = settings::current(app);
+
+    let mut title = if account.id == DEFAULT_ACCOUNT_ID {
+        "Messterial".to_string()
+    } else {
+        format!("Messterial - {}", account.name)
+    };
+    if safe_mode {
+        title.push_str(" (Safe Mode)");
+    }
+
+    // Safe mode has no custom titlebar, so it needs the native one
+    let mut builder = WebviewWindowBuilder::new(app, &label, messenger_url())
+        .title(title)
+        .inner_size(1200.0, 800.0)
+        .decorations(safe_mode || !settings.features.titlebar)
+        .devtools(cli::devtools_enabled(app));
+
+    if !safe_mode {
+        builder = builder.initialization_script(injection::init_script(
+            account,
+            AccountLayout::Windows,
+            &settings,
+            &user_content::current(app),
+            zoom::current(app, &label).font_scale,
+        ));
+        builder = builder.on_page_load(|window, payload| {
+            user_content::on_page_load(window.as_ref(), &payload);
+        });
+    }
+
+    // The custom titlebar sits under the traffic lights on macOS
+    #[cfg(target_os = "macos")]
+    if !safe_mode && settings.features.titlebar {
+        builder = builder
+            .decorations(true)
+            .title_bar_style(tauri::TitleBarStyle::Overlay)
+            .hidden_title(true);
+    }
+
+    let window = with_account_profile(builder, app, account)?
+        .on_new_window(popup_handler(app.clone()))
+        .on_download(downloads::on_download)
+        .build()?;
+    zoom::restore(window.as_ref());
+    downloads::attach(window.as_ref());
+    if !safe_mode {
+        permissions::attach(window.as_ref());
+    }
+    calls::guard_close(&window.as_ref().window());
+    set_active(app, &account.id);
+
+    // Links and forwarded arguments go to whichever account was used last
+    let app_handle = app.clone();
+    let account_id = account.id.clone();
+    window.on_window_event(move |event| {
+        if let WindowEvent::Focused(true) = event {
+            set_active(&app_handle, &account_id);
+        }
+    });
+
+    Ok(window)
+}
+
+/// Label of the child webview hosting an account in the tabbed layout.
+pub fn tab_label(account_id: &str) -> String {
+    format!("tab-{}", account_id)
+}
+
+/// Label of the window a conversation of an account is popped out into, from
+/// the path of its URL. Labels can't hold dots, which thread ids can.
+pub fn conversation_label(account_id: &str, url: &Url) -> String {
+    let thread = url
+        .path()
+        .trim_matches('/')
+        .replace('/', "-")
+        .replace('.', "_");
+    format!("chat:{}:{}", account_id, thread)
+}
+
+/// Maps a window or tab label back to the id of the account it shows.
+pub fn account_id_for_label(label: &str) -> Option<String> {
+    if label == "main" {
+        Some(DEFAULT_ACCOUNT_ID.into())
+    } else if let Some(conversation) = label.strip_prefix("chat:") {
+        conversation
+            .split_once(':')
+            .map(|(account_id, _)| account_id.to_string())
+    } else {
+        label
+            .strip_prefix("account-")
+            .or_else(|| label.strip_prefix("tab-"))
+            .map(String::from)
+    }
+}
+
+/// Opens a conversation of `account` in a compact window of its own, without
+/// the navigation card. It shares the account's session, and its size is
+/// remembered per conversation by the window-state plugin.
+pub fn open_conversation_window<R: Runtime>(
+    app: &AppHandle<R>,
+    account: &Account,
+    url: Url,
+) -> tauri::Result<WebviewWindow<R>> {
+    let label = conversation_label(&account.id, &url);
+
+    if let Some(window) = app.get_webview_window(&label) {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+        return Ok(window);
+    }
+
+    let safe_mode = is_safe_mode(app);
+    let settings = settings::current(app);
+
+    let mut builder = WebviewWindowBuilder::new(app, &label, WebviewUrl::External(url))
+        .title("Messenger")
+        .inner_size(420.0, 640.0)
+        .min_inner_size(320.0, 400.0)
+        .decorations(safe_mode || !settings.features.titlebar)
+        .devtools(cli::devtools_enabled(app))
+        .on_document_title_changed(|window, title| {
+            let _ = window.set_title(&title);
+        });
+
+    if !safe_mode {
+        builder = builder
+            .initialization_script(injection::init_script(
+                account,
+                AccountLayout::Windows,
+                &settings,
+                &user_content::current(app),
+                zoom::current(app, &label).font_scale,
+            ))
+            .initialization_script(injection::POPOUT_SCRIPT)
+            .on_page_load(|window, payload| {
+                user_content::on_page_load(window.as_ref(), &payload);
+            });
+    }
+
+    #[cfg(target_os = "macos")]
+    if !safe_mode && settings.features.titlebar {
+        builder = builder
+            .decorations(true)
+            .title_bar_style(tauri::TitleBarStyle::Overlay)
+            .hidden_title(true);
+    }
+
+    let window = with_account_profile(builder, app, account)?
+        .on_new_window(popup_handler(app.clone()))
+        .on_download(downloads::on_download)
+        .build()?;
+    zoom::restore(window.as_ref());
+    downloads::attach(window.as_ref());
+    if !safe_mode {
+        permissions::attach(window.as_ref());
+    }
+
+    Ok(window)
+}
+
+/// Every open webview showing a Messenger account, in either layout.
+pub fn account_webviews<R: Runtime>(app: &AppHandle<R>) -> Vec<Webview<R>> {
+    app.webviews()
+        .into_values()
+        .filter(|webview| account_id_for_label(webview.label()).is_some())
+        .collect()
+}
+
+/// Returns the single `main` window hosting the account tabs, creating it if needed.
+fn tabs_window<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<Window<R>> {
+    if let Some(window) = app.get_window("main") {
+        return Ok(window);
+    }
+
+    let builder = WindowBuilder::new(app, "main")
+        .title("Messterial")
+        .inner_size(1200.0, 800.0);
+
+    #[cfg(target_os = "macos")]
+    let builder = builder
+        .title_bar_style(tauri::TitleBarStyle::Overlay)
+        .hidden_title(true);
+    #[cfg(not(target_os = "macos"))]
+    let builder = builder.decorations(false);
+
+    let window = builder.build()?;
+    calls::guard_close(&window);
+    Ok(window)
+}
+
+/// Shows the tab of an account inside the `main` window, creating its webview
+/// if it isn't open yet. Every other tab gets hidden.
+pub fn open_account_tab<R: Runtime>(
+    app: &AppHandle<R>,
+    account: &Account,
+) -> tauri::Result<Webview<R>> {
+    let window = tabs_window(app)?;
+    let label = tab_label(&account.id);
+
+    let webview = match app.get_webview(&label) {
+        Some(webview) => webview,
+        None => {
+            let settings = settings::current(app);
+            let builder = WebviewBuilder::new(&label, messenger_url())
+                .auto_resize()
+                .devtools(cli::devtools_enabled(app))
+                .initialization_script(injection::init_script(
+                    account,
+                    AccountLayout::Tabs,
+                    &settings,
+                    &user_content::current(app),
+                    zoom::current(app, window.label()).font_scale,
+                ))
+                .on_page_load(|webview, payload| {
+                    user_content::on_page_load(&webview, &payload);
+                });
+
+            let builder = with_account_profile(builder, app, account)?
+                .on_new_window(popup_handler(app.clone()))
+                .on_download(downloads::on_download);
+            let webview = window.add_child(
+                builder,
+                LogicalPosition::new(0.0, 0.0),
+                window.inner_size()?,
+            )?;
+            zoom::restore(&webview);
+            downloads::attach(&webview);
+            permissions::attach(&webview);
+            webview
+        }
+    };
+
+    for other in window.webviews() {
+        if other.label() != label {
+            let _ = other.hide();
+        }
+    }
+
+    let _ = webview.show();
+    let _ = window.unminimize();
+    let _ = window.show();
+    let _ = window.set_focus();
+    let _ = webview.set_focus();
+
+    set_active(app, &account.id);
+
+    Ok(webview)
+}
+
+/// Opens every configured account using the configured layout, then brings
+/// `focus` (or the default account) to the front.
+pub fn open_all<R: Runtime>(
+    app: &AppHandle<R>,
+    manager: &AccountManager,
+    focus: Option<&Account>,
+) -> tauri::Result<()> {
+    let accounts = manager.accounts();
+
+    match manager.layout() {
+        AccountLayout::Windows => {
+            for account in &accounts {
+                open_account_window(app, account)?;
+            }
+        }
+        AccountLayout::Tabs => {
+            for account in &accounts {
+                open_account_tab(app, account)?;
+            }
+        }
+    }
+
+    let focus = focus.cloned().or_else(|| manager.get(DEFAULT_ACCOUNT_ID));
+    if let Some(account) = focus {
+        show_account(app, &account)?;
+    }
+
+    Ok(())
+}
+
+fn set_active<R: Runtime>(app: &AppHandle<R>, account_id: &str) {
+    if let Some(manager) = app.try_state::<AccountManager>() {
+        *manager.active.lock().unwrap() = account_id.to_string();
+    }
+}
+
+fn is_safe_mode<R: Runtime>(app: &AppHandle<R>) -> bool {
+    app.try_state::<SafeMode>()
+        .map(|safe_mode| safe_mode.is_active())
+        .unwrap_or_default()
+}
+
+/// Webview of the account that was used last: its window, or its tab in the
+/// tabbed layout. This is the default account's `main` window until the user
+/// switches accounts.
+pub fn active_webview<R: Runtime>(app: &AppHandle<R>) -> Option<Webview<R>> {
+    let manager = app.try_state::<AccountManager>()?;
+    let active = manager.active.lock().unwrap().clone();
+    match manager.layout() {
+        AccountLayout::Windows => app.get_webview(&window_label(&active)),
+        AccountLayout::Tabs => app.get_webview(&tab_label(&active)),
+    }
+}
+
+/// Restores and focuses the window of the account that was used last.
+pub fn focus_active<R: Runtime>(app: &AppHandle<R>) {
+    let Some(manager) = app.try_state::<AccountManager>() else {
+        return;
+    };
+    let label = match manager.layout() {
+        AccountLayout::Windows => window_label(&manager.active.lock().unwrap()),
+        AccountLayout::Tabs => "main".to_string(),
+    };
+
+    if let Some(window) = app.get_window(&label) {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+/// Finds an account by id or (case-insensitive) name.
+pub fn find_account(manager: &AccountManager, query: &str) -> Option<Account> {
+    manager.get(query).or_else(|| {
+        manager
+            .accounts()
+            .into_iter()
+            .find(|a| a.name.eq_ignore_ascii_case(query))
+    })
+}
+
+/// Brings an account to the front, in its own window or as the active tab.
+pub fn show_account<R: Runtime>(app: &AppHandle<R>, account: &Account) -> tauri::Result<()> {
+    let layout = app
+        .try_state::<AccountManager>()
+        .map(|manager| manager.layout())
+        .unwrap_or_default();
+
+    match layout {
+        AccountLayout::Windows => open_account_window(app, account).map(|_| ()),
+        AccountLayout::Tabs => open_account_tab(app, account).map(|_| ()),
+    }
+}
+
+// =========================================================================
+//                               COMMANDS
+// =========================================================================
+
+#[tauri::command]
+pub fn list_accounts(manager: tauri::State<'_, AccountManager>) -> Vec<Account> {
+    manager.accounts()
+}
+
+#[tauri::command]
+pub fn add_account<R: Runtime>(
+    app: AppHandle<R>,
+    manager: tauri::State<'_, AccountManager>,
+    name: String,
+) -> Result<Account, String> {
+    if name.trim().is_empty() {
+        return Err("Account name cannot be empty".into());
+    }
+    let account = manager.add(&name);
+    let _ = app.emit("accounts-changed", manager.accounts());
+    Ok(account)
+}
+
+/// Asks the user with a native dialog over `window`, which the page can't
+/// answer by itself, whether `account` and its session data should go.
+async fn confirm_removal<R: Runtime>(window: &Window<R>, account: &Account) -> bool {
+    let answer = rfd::AsyncMessageDialog::new()
+        .set_level(rfd::MessageLevel::Warning)
+        .set_title("Messterial")
+        .set_description(format!(
+            "Remove {}? Its session data will be deleted.",
+            account.name
+        ))
+        .set_buttons(rfd::MessageButtons::OkCancelCustom(
+            REMOVE.into(),
+            CANCEL.into(),
+        ))
+        .set_parent(window)
+        .show()
+        .await;
+    // Some platforms answer with the standard buttons even when they are renamed
+    match answer {
+        rfd::MessageDialogResult::Ok => true,
+        rfd::MessageDialogResult::Custom(button) => button == REMOVE,
+        _ => false,
+    }
+}
+
+/// Removes an account once the user confirms it. Returns whether it's gone.
+#[tauri::command]
+pub async fn remove_account<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    manager: tauri::State<'_, AccountManager>,
+    id: String,
+) -> Result<bool, String> {
+    let account = manager
+        .get(&id)
+        .filter(|account| account.id != DEFAULT_ACCOUNT_ID)
+        .ok_or_else(|| format!("Cannot remove account '{}'", id))?;
+    if !confirm_removal(&window, &account).await {
+        return Ok(false);
+    }
+    if !manager.remove(&id) {
+        return Err(format!("Cannot remove account '{}'", id));
+    }
+
+    if let Some(window) = app.get_webview_window(&window_label(&id)) {
+        let _ = window.destroy();
+    }
+    if let Some(webview) = app.get_webview(&tab_label(&id)) {
+        let _ = webview.close();
+    }
+    let conversations = format!("chat:{}:", id);
+    for (label, window) in app.webview_windows() {
+        if label.starts_with(&conversations) {
+            let _ = window.destroy();
+        }
+    }
+    manager.unread.lock().unwrap().remove(&id);
+    let _ = app.emit("accounts-changed", manager.accounts());
+
+    let active = manager.active.lock().unwrap().clone();
+    if active == id {
+        if let Some(account) = manager.get(DEFAULT_ACCOUNT_ID) {
+            let _ = show_account(&app, &account);
+        }
+    }
+
+    // The windows are gone, but their webview processes may still hold files
+    if !remove_profile(&app, &id) {
+        log::info!("Removing the profile of {} on the next start", id);
+        manager.schedule_removal(&id);
+    }
+
+    Ok(true)
+}
+
+#[tauri::command]
+pub async fn switch_account<R: Runtime>(
+    app: AppHandle<R>,
+    manager: tauri::State<'_, AccountManager>,
+    id: String,
+) -> Result<(), String> {
+    let account = manager
+        .get(&id)
+        .ok_or_else(|| format!("Unknown account '{}'", id))?;
+    show_account(&app, &account).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_account_layout<R: Runtime>(
+    app: AppHandle<R>,
+    manager: tauri::State<'_, AccountManager>,
+    layout: AccountLayout,
+) {
+    if manager.config.lock().unwrap().layout != layout {
+        manager.set_layout(layout);
+        log::info!("Account layout changed, restarting...");
+        safe_mode::restart(&app);
+    }
+}
+
+/// Called by every Messenger webview with its own unread count. Returns the
+/// count the webview should show on the window badge: its own in the windowed
+/// layout, the sum over all accounts when they share a window as tabs.
+///
+/// Popped out conversations see the same account-wide count as the account's
+/// window, which may be stale while it isn't focused, so only the account's
+/// own window or tab is recorded.
+#[tauri::command]
+pub fn report_unread<R: Runtime>(
+    app: AppHandle<R>,
+    webview: Webview<R>,
+    manager: tauri::State<'_, AccountManager>,
+    count: u32,
+) -> u32 {
+    let label = webview.label();
+    let Some(id) = account_id_for_label(label).filter(|_| !label.starts_with("chat:")) else {
+        return count;
+    };
+
+    let counts = {
+        let mut unread = manager.unread.lock().unwrap();
+        if unread.get(&id) == Some(&count) {
+            None
+        } else {
+            unread.insert(id, count);
+            Some(unread.clone())
+        }
+    };
+
+    if let Some(counts) = counts {
+        let _ = app.emit("accounts-unread", counts);
+    }
+
+    match manager.layout() {
+        AccountLayout::Windows => count,
+        AccountLayout::Tabs => manager.unread.lock().unwrap().values().sum(),
+    }
+}
+
+#[tauri::command]
+pub fn unread_counts(manager: tauri::State<'_, AccountManager>) -> HashMap<String, u32> {
+    manager.unread.lock().unwrap().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(url: &str) -> Url {
+        url.parse().unwrap()
+    }
+
+    #[test]
+    fn conversation_label_flattens_the_thread_path() {
+        assert_eq!(
+            conversation_label("work", &url("https://www.messenger.com/t/123/")),
+            "chat:work:t-123"
+        );
+        assert_eq!(
+            conversation_label("work", &url("https://www.messenger.com/e2ee/t/1.5")),
+            "chat:work:e2ee-t-1_5"
+        );
+    }
+
+    #[test]
+    fn account_labels_round_trip() {
+        let thread = url("https://www.messenger.com/e2ee/t/123");
+        for id in [DEFAULT_ACCOUNT_ID, "work", "account-2", "tab-chat"] {
+            assert_eq!(account_id_for_label(&tab_label(id)).as_deref(), Some(id));
+            assert_eq!(
+                account_id_for_label(&conversation_label(id, &thread)).as_deref(),
+                Some(id)
+            );
+        }
+        assert_eq!(
+            account_id_for_label(&window_label(DEFAULT_ACCOUNT_ID)).as_deref(),
+            Some(DEFAULT_ACCOUNT_ID)
+        );
+        assert_eq!(
+            account_id_for_label(&window_label("account-2")).as_deref(),
+            Some("account-2")
+        );
+    }
+
+    #[test]
+    fn other_windows_belong_to_no_account() {
+        for label in ["call", "call-3", "auth-1", "media", "share-picker", "chat:"] {
+            assert_eq!(account_id_for_label(label), None, "{}", label);
+        }
+    }
+
+    #[test]
+    fn slugify_makes_ids_safe_for_labels() {
+        assert_eq!(slugify("  Work Account! "), "work-account");
+        assert_eq!(slugify("Zażółć"), "za");
+        assert_eq!(slugify("***"), "account");
+    }
+}
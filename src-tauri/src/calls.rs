@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::{
+    AppHandle, LogicalSize, Manager, PhysicalPosition, PhysicalSize, Runtime, WebviewWindow,
+    Window, WindowEvent,
+};
+
+use crate::url_policy::PopupKind;
+use crate::{safe_mode, screen_share};
+
+/// Size of a call window in picture-in-picture mode.
+const PIP_SIZE: LogicalSize<f64> = LogicalSize {
+    width: 320.0,
+    height: 200.0,
+};
+
+/// Space between a picture-in-picture window and the edges of the screen.
+const PIP_MARGIN: f64 = 24.0;
+
+/// Where a call window was before it went picture-in-picture.
+#[derive(Debug, Clone, Copy)]
+struct PipRestore {
+    position: PhysicalPosition<i32>,
+    size: PhysicalSize<u32>,
+    always_on_top: bool,
+}
+
+/// State of the open call windows.
+#[derive(Default)]
+pub struct CallWindows {
+    pip: Mutex<HashMap<String, PipRestore>>,
+    /// An update was installed during a call, restart once it ends.
+    restart_pending: AtomicBool,
+}
+
+/// Whether `label` belongs to a call window.
+pub fn is_call_window(label: &str) -> bool {
+    let call = PopupKind::Call.label();
+    label == call
+        || label
+            .strip_prefix(call)
+            .is_some_and(|rest| rest.starts_with('-'))
+}
+
+fn call_windows<R: Runtime>(app: &AppHandle<R>) -> impl Iterator<Item = WebviewWindow<R>> {
+    app.webview_windows()
+        .into_iter()
+        .filter(|(label, _)| is_call_window(label))
+        .map(|(_, window)| window)
+}
+
+/// Whether a call is going on in any window.
+pub fn is_call_active<R: Runtime>(app: &AppHandle<R>) -> bool {
+    call_windows(app).next().is_some()
+}
+
+/// Brings the first call window to the front.
+pub fn focus_call<R: Runtime>(app: &AppHandle<R>) {
+    if let Some(window) = call_windows(app).next() {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+/// Restarts the app right away, or once the last call ends.
+pub fn restart_when_idle<R: Runtime>(app: &AppHandle<R>) {
+    if is_call_active(app) {
+        log::info!("Restart postponed until the call ends");
+        if let Some(state) = app.try_state::<CallWindows>() {
+            state.restart_pending.store(true, Ordering::SeqCst);
+        }
+    } else {
+        safe_mode::restart(app);
+    }
+}
+
+/// Keeps a Messenger window open while a call is going on, since the call
+/// runs in its session. The call window is shown instead.
+pub fn guard_close<R: Runtime>(window: &Window<R>) {
+    let app = window.app_handle().clone();
+    window.on_window_event(move |event| {
+        if let WindowEvent::CloseRequested { api, .. } = event {
+            if is_call_active(&app) {
+                log::info!("Not closing the window during a call");
+                api.prevent_close();
+                focus_call(&app);
+            }
+        }
+    });
+}
+
+/// Sets up a newly created call window.
+pub fn on_created<R: Runtime>(window: &WebviewWindow<R>) {
+    let app = window.app_handle().clone();
+    let label = window.label().to_string();
+    let handle = window.clone();
+
+    window.on_window_event(move |event| match event {
+        // The window-state plugin shouldn't remember the picture-in-picture size
+        WindowEvent::CloseRequested { .. } => {
+            let restore = app
+                .try_state::<CallWindows>()
+                .and_then(|state| state.pip.lock().unwrap().remove(&label));
+            if let Some(restore) = restore {
+                let _ = handle.set_size(restore.size);
+                let _ = handle.set_position(restore.position);
+            }
+        }
+        WindowEvent::Destroyed => {
+            let Some(state) = app.try_state::<CallWindows>() else {
+                return;
+            };
+            state.pip.lock().unwrap().remove(&label);
+            screen_share::stop_window(&app, &label);
+
+            let other_calls = call_windows(&app).any(|window| window.label() != label);
+            if !other_calls && state.restart_pending.load(Ordering::SeqCst) {
+                log::info!("Call ended, restarting to finish the update");
+                safe_mode::restart(&app);
+            }
+        }
+        _ => {}
+    });
+}
+
+/// Fails unless `window` is a call window, so Messenger's other pages can't
+/// move or pin their window.
+fn require_call_window<R: Runtime>(window: &Window<R>) -> Result<(), String> {
+    if is_call_window(window.label()) {
+        Ok(())
+    } else {
+        Err("Only call windows have call controls".into())
+    }
+}
+
+// =========================================================================
+//                               COMMANDS
+// =========================================================================
+
+#[tauri::command]
+pub fn toggle_call_always_on_top<R: Runtime>(window: Window<R>) -> Result<bool, String> {
+    require_call_window(&window)?;
+    let on_top = !window.is_always_on_top().map_err(|e| e.to_string())?;
+    window
+        .set_always_on_top(on_top)
+        .map_err(|e| e.to_string())?;
+    Ok(on_top)
+}
+
+/// Shrinks the call window into the bottom right corner of its screen, on top
+/// of everything, or puts it back where it was. Returns whether it's in
+/// picture-in-picture mode now.
+#[tauri::command]
+pub fn toggle_call_pip<R: Runtime>(
+    window: Window<R>,
+    state: tauri::State<'_, CallWindows>,
+) -> Result<bool, String> {
+    require_call_window(&window)?;
+    let mut pip = state.pip.lock().unwrap();
+
+    if let Some(restore) = pip.remove(window.label()) {
+        window.set_size(restore.size).map_err(|e| e.to_string())?;
+        window
+            .set_position(restore.position)
+            .map_err(|e| e.to_string())?;
+        window
+            .set_always_on_top(restore.always_on_top)
+            .map_err(|e| e.to_string())?;
+        return Ok(false);
+    }
+
+    let Some(monitor) = window.current_monitor().map_err(|e| e.to_string())? else {
+        return Err("The call window isn't on any screen".into());
+    };
+
+    pip.insert(
+        window.label().to_string(),
+        PipRestore {
+            position: window.outer_position().map_err(|e| e.to_string())?,
+            size: window.inner_size().map_err(|e| e.to_string())?,
+            always_on_top: window.is_always_on_top().map_err(|e| e.to_string())?,
+        },
+    );
+
+    let scale = monitor.scale_factor();
+    let size: PhysicalSize<u32> = PIP_SIZE.to_physical(scale);
+    let margin = (PIP_MARGIN * scale) as i32;
+    let area = monitor.work_area();
+    let position = PhysicalPosition::new(
+        area.position.x + area.size.width as i32 - size.width as i32 - margin,
+        area.position.y + area.size.height as i32 - size.height as i32 - margin,
+    );
+
+    window.set_always_on_top(true).map_err(|e| e.to_string())?;
+    window.set_size(size).map_err(|e| e.to_string())?;
+    window.set_position(position).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn call_window_labels() {
+        assert!(is_call_window("call"));
+        assert!(is_call_window("call-3"));
+        assert!(!is_call_window("calls"));
+        assert!(!is_call_window("callback-1"));
+        assert!(!is_call_window("main"));
+        assert!(!is_call_window("account-call"));
+        assert!(!is_call_window("chat:call:1"));
+    }
+}
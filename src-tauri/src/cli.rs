@@ -0,0 +1,65 @@
+use clap::Parser;
+use log::LevelFilter;
+use tauri::{AppHandle, Manager, Runtime};
+
+/// Startup options, parsed from the command line of the first launch or
+/// forwarded from a second one.
+#[derive(Debug, Clone, Parser)]
+#[command(
+    name = "messterial",
+    version,
+    about = "A material 3-ish wrapper for Meta Messenger"
+)]
+pub struct Options {
+    /// Start with the windows minimized
+    #[arg(long)]
+    pub minimized: bool,
+
+    /// Account to open, by id or name
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Thread to open, e.g. https://www.messenger.com/t/<id> or messterial://t/<id>
+    #[arg(long, value_name = "THREAD")]
+    pub url: Option<String>,
+
+    /// Don't check for updates
+    #[arg(long)]
+    pub no_update: bool,
+
+    /// Open the webview devtools
+    #[arg(long)]
+    pub devtools: bool,
+
+    /// Log verbosity: off, error, warn, info, debug or trace
+    #[arg(long, value_name = "LEVEL", default_value_t = LevelFilter::Info)]
+    pub log_level: LevelFilter,
+
+    /// Forget saved window sizes and positions
+    #[arg(long)]
+    pub reset_window_state: bool,
+
+    /// Load Messenger without any injected CSS or scripts
+    #[arg(long)]
+    pub safe_mode: bool,
+
+    /// Links handed over by the OS, e.g. messterial://t/<id>
+    #[arg(hide = true)]
+    pub links: Vec<String>,
+}
+
+impl Options {
+    /// Thread links to open, `--url` first.
+    pub fn thread_links(&self) -> Vec<String> {
+        self.url.iter().chain(self.links.iter()).cloned().collect()
+    }
+}
+
+/// Whether webviews get devtools: always in debug builds, and only with
+/// `--devtools` in release builds.
+pub fn devtools_enabled<R: Runtime>(app: &AppHandle<R>) -> bool {
+    cfg!(debug_assertions)
+        || app
+            .try_state::<Options>()
+            .is_some_and(|options| options.devtools)
+}
@@ -0,0 +1,58 @@
+use tauri::{AppHandle, Runtime, Url};
+use tauri_plugin_deep_link::DeepLinkExt;
+
+use crate::{accounts, url_policy};
+
+/// Registers the `messterial://` and `messenger://` schemes and opens the
+/// links delivered to the running app. Elsewhere than on macOS, links the app
+/// was launched with are on the command line and handled with the other
+/// arguments, see `cli::Options::thread_links`.
+pub fn setup<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<()> {
+    // Installed bundles register the schemes themselves, but dev builds and
+    // AppImages need it done at runtime
+    #[cfg(any(windows, target_os = "linux"))]
+    if let Err(e) = app.deep_link().register_all() {
+        log::error!("Failed to register URL schemes: {}", e);
+    }
+
+    // macOS delivers links through events instead of the command line
+    let app_handle = app.clone();
+    app.deep_link().on_open_url(move |event| {
+        let links: Vec<String> = event.urls().iter().map(Url::to_string).collect();
+        handle_links(&app_handle, &links);
+    });
+
+    // The launch link isn't an argument on macOS
+    #[cfg(target_os = "macos")]
+    match app.deep_link().get_current() {
+        Ok(Some(urls)) => {
+            let links: Vec<String> = urls.iter().map(Url::to_string).collect();
+            handle_links(app, &links);
+        }
+        Ok(None) => {}
+        Err(e) => log::error!("Failed to read launch URL: {}", e),
+    }
+
+    Ok(())
+}
+
+/// Opens the first link the URL policy accepts in the active account's webview.
+pub fn handle_links<R: Runtime>(app: &AppHandle<R>, links: &[String]) {
+    if let Some(url) = links.iter().find_map(|link| url_policy::resolve_link(link)) {
+        accounts::focus_active(app);
+        open_url(app, url);
+    }
+}
+
+/// Navigates the active account's webview to a Messenger URL.
+fn open_url<R: Runtime>(app: &AppHandle<R>, url: Url) {
+    match accounts::active_webview(app) {
+        Some(webview) => {
+            log::info!("Opening link: {}", url);
+            if let Err(e) = webview.navigate(url) {
+                log::error!("Failed to open link: {}", e);
+            }
+        }
+        None => log::error!("No account webview to open {} in", url),
+    }
+}
@@ -0,0 +1,998 @@
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::webview::DownloadEvent;
+use tauri::window::{ProgressBarState, ProgressBarStatus};
+use tauri::{AppHandle, Emitter, Manager, Runtime, Url, Webview, Window};
+use tauri_plugin_notification::NotificationExt;
+use tauri_plugin_opener::OpenerExt;
+
+use crate::settings::{self, SettingsStore};
+
+const DOWNLOADS_FILE: &str = "downloads.json";
+
+/// Downloads kept in the list, the oldest ones are forgotten first.
+const MAX_ENTRIES: usize = 100;
+
+/// Longest file name written, in bytes. Most file systems allow 255.
+const MAX_FILE_NAME_BYTES: usize = 200;
+
+/// Longer suffixes are kept as part of the name rather than as an extension,
+/// so cutting the name down to size can't leave only the suffix.
+const MAX_EXTENSION_BYTES: usize = 16;
+
+/// Buttons of the dialog confirming that a download should be opened.
+const OPEN: &str = "Open";
+const CANCEL: &str = "Cancel";
+
+/// Used when nothing of the suggested name is left.
+const FALLBACK_FILE_NAME: &str = "download";
+
+/// Names Windows reserves for devices, with or without an extension.
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Downloads without a total size report progress every this many bytes.
+const PROGRESS_STEP: u64 = 256 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadState {
+    Downloading,
+    Finished,
+    /// Failed, cancelled, or interrupted by quitting the app.
+    Failed,
+}
+
+/// Where a download goes. Downloads to ask about start in a temporary file
+/// next to the others while the save dialog is open, and are moved once both
+/// the download and the dialog are done.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+enum Destination {
+    /// `path` is where the file stays.
+    #[default]
+    Final,
+    /// The save dialog is open. `finished` tells whether the download
+    /// succeeded once the webview is done with it.
+    Asking { finished: Option<bool> },
+    /// Picked while the webview was still downloading, `None` when cancelled.
+    Chosen(Option<PathBuf>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Download {
+    pub id: u32,
+    pub file_name: String,
+    pub path: PathBuf,
+    pub url: String,
+    pub state: DownloadState,
+    pub received: u64,
+    /// Size of the file, when the server sent it.
+    pub total: Option<u64>,
+    /// Unix timestamp in seconds.
+    pub started: u64,
+    /// Label of the window that started the download.
+    #[serde(skip)]
+    window: String,
+    /// Last progress sent to the pages, a percentage or a step of bytes.
+    #[serde(skip)]
+    reported: u64,
+    #[serde(skip)]
+    destination: Destination,
+}
+
+impl Download {
+    fn progress_mark(&self) -> u64 {
+        match self.total {
+            Some(total) => self.received * 100 / total.max(1),
+            None => self.received / PROGRESS_STEP,
+        }
+    }
+
+    fn complete(&mut self, success: bool) {
+        if success {
+            self.state = DownloadState::Finished;
+            if let Ok(metadata) = std::fs::metadata(&self.path) {
+                self.received = metadata.len();
+                self.total = Some(metadata.len());
+            }
+        } else {
+            self.state = DownloadState::Failed;
+        }
+    }
+
+    /// Moves the temporary file of a download asked about to `target`.
+    fn move_to(&mut self, target: PathBuf) -> bool {
+        let moved = std::fs::rename(&self.path, &target).or_else(|_| {
+            std::fs::copy(&self.path, &target)?;
+            std::fs::remove_file(&self.path)
+        });
+
+        match moved {
+            Ok(()) => {
+                self.file_name = target
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                self.path = target;
+                true
+            }
+            Err(e) => {
+                log::error!(
+                    "Failed to move {} to {}: {}",
+                    self.path.display(),
+                    target.display(),
+                    e
+                );
+                false
+            }
+        }
+    }
+}
+
+/// Sent with `download-progress` while a file is downloading.
+#[derive(Debug, Clone, Serialize)]
+struct Progress {
+    id: u32,
+    received: u64,
+    total: Option<u64>,
+}
+
+/// Attachments downloaded from Messenger, newest first.
+pub struct DownloadStore {
+    next_id: AtomicU32,
+    downloads: Mutex<Vec<Download>>,
+    path: PathBuf,
+}
+
+impl DownloadStore {
+    pub fn load<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<Self> {
+        let path = app.path().app_local_data_dir()?.join(DOWNLOADS_FILE);
+
+        let mut downloads: Vec<Download> = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+
+        // Whatever was downloading when the app quit never finished
+        for download in &mut downloads {
+            if download.state == DownloadState::Downloading {
+                download.state = DownloadState::Failed;
+            }
+        }
+        let next_id = downloads.iter().map(|d| d.id + 1).max().unwrap_or_default();
+
+        Ok(Self {
+            next_id: AtomicU32::new(next_id),
+            downloads: Mutex::new(downloads),
+            path,
+        })
+    }
+
+    pub fn list(&self) -> Vec<Download> {
+        self.downloads.lock().unwrap().clone()
+    }
+
+    fn get(&self, id: u32) -> Option<Download> {
+        self.downloads
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|download| download.id == id)
+            .cloned()
+    }
+
+    fn save(&self, downloads: &[Download]) {
+        if let Some(dir) = self.path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        match serde_json::to_string(downloads) {
+            Ok(raw) => {
+                if let Err(e) = std::fs::write(&self.path, raw) {
+                    log::error!("Failed to save downloads: {}", e);
+                }
+            }
+            Err(e) => log::error!("Failed to serialize downloads: {}", e),
+        }
+    }
+
+    fn start(&self, url: &Url, path: PathBuf, window: &str, destination: Destination) -> Download {
+        let download = Download {
+            id: self.next_id.fetch_add(1, Ordering::SeqCst),
+            file_name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            path,
+            url: url.to_string(),
+            state: DownloadState::Downloading,
+            received: 0,
+            total: None,
+            started: now(),
+            window: window.to_string(),
+            reported: 0,
+            destination,
+        };
+
+        let mut downloads = self.downloads.lock().unwrap();
+        downloads.insert(0, download.clone());
+        downloads.truncate(MAX_ENTRIES);
+        self.save(&downloads);
+        download
+    }
+
+    /// Marks the newest download of `url` as done. macOS doesn't say where the
+    /// file went, so `path` only overrides the one picked when it started.
+    /// Returns it unless it still waits for the save dialog or was cancelled.
+    fn finish(&self, url: &Url, path: Option<PathBuf>, success: bool) -> Option<Download> {
+        let mut downloads = self.downloads.lock().unwrap();
+        let index = downloads.iter().position(|download| {
+            download.state == DownloadState::Downloading && download.url == url.as_str()
+        })?;
+        let download = &mut downloads[index];
+
+        if let Some(path) = path {
+            download.path = path;
+        }
+        let success = match std::mem::take(&mut download.destination) {
+            Destination::Final => success,
+            Destination::Asking { .. } => {
+                download.destination = Destination::Asking {
+                    finished: Some(success),
+                };
+                return None;
+            }
+            Destination::Chosen(Some(target)) if success => download.move_to(target),
+            Destination::Chosen(target) => {
+                let _ = std::fs::remove_file(&download.path);
+                if target.is_none() {
+                    downloads.remove(index);
+                    self.save(&downloads);
+                    return None;
+                }
+                false
+            }
+        };
+        download.complete(success);
+
+        let download = download.clone();
+        self.save(&downloads);
+        Some(download)
+    }
+
+    /// Records where the user wants the download `id`, `None` when they
+    /// cancelled. Returns it when this completes it.
+    fn choose(&self, id: u32, target: Option<PathBuf>) -> Option<Download> {
+        let mut downloads = self.downloads.lock().unwrap();
+        let index = downloads.iter().position(|download| download.id == id)?;
+        let download = &mut downloads[index];
+
+        let Destination::Asking { finished } = download.destination else {
+            return None;
+        };
+        let success = match (finished, target) {
+            (None, target) => {
+                download.destination = Destination::Chosen(target);
+                return None;
+            }
+            (Some(true), Some(target)) => download.move_to(target),
+            (Some(_), target) => {
+                let _ = std::fs::remove_file(&download.path);
+                if target.is_none() {
+                    downloads.remove(index);
+                    self.save(&downloads);
+                    return None;
+                }
+                false
+            }
+        };
+        download.destination = Destination::Final;
+        download.complete(success);
+
+        let download = download.clone();
+        self.save(&downloads);
+        Some(download)
+    }
+
+    /// Updates the progress of the newest download of `url`. Returns it when
+    /// the change is worth showing.
+    fn progress(&self, url: &str, received: u64, total: Option<u64>) -> Option<Download> {
+        let mut downloads = self.downloads.lock().unwrap();
+        let download = downloads
+            .iter_mut()
+            .find(|download| download.state == DownloadState::Downloading && download.url == url)?;
+
+        download.received = received;
+        download.total = total.or(download.total);
+        let mark = download.progress_mark();
+        if mark == download.reported {
+            return None;
+        }
+        download.reported = mark;
+        Some(download.clone())
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+/// Makes a file name suggested by a page safe to write on every platform: no
+/// path separators, control or reserved characters, no trailing dots or
+/// spaces, no device names and not too long.
+fn sanitize_file_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | '<' | '>' | ':' | '"' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let cleaned = cleaned
+        .trim()
+        .trim_start_matches('.')
+        .trim_end_matches(['.', ' ']);
+
+    let (stem, extension) = match split_extension(cleaned) {
+        (_, extension) if extension.len() > MAX_EXTENSION_BYTES => (cleaned, ""),
+        split => split,
+    };
+
+    let mut end = MAX_FILE_NAME_BYTES
+        .saturating_sub(extension.len())
+        .min(stem.len());
+    while !stem.is_char_boundary(end) {
+        end -= 1;
+    }
+    let stem = match stem[..end].trim_end_matches(['.', ' ']) {
+        "" => FALLBACK_FILE_NAME,
+        stem => stem,
+    };
+    let mut name = format!("{}{}", stem, extension);
+
+    let device = name.split('.').next().unwrap_or_default();
+    if RESERVED_NAMES
+        .iter()
+        .any(|reserved| device.eq_ignore_ascii_case(reserved))
+    {
+        name.insert(0, '_');
+    }
+    name
+}
+
+/// `photo.jpg` -> (`photo`, `.jpg`).
+fn split_extension(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(index) if index > 0 => name.split_at(index),
+        _ => (name, ""),
+    }
+}
+
+/// `name` in `folder`, numbered like `photo (1).jpg` if it's taken.
+fn unique_path(folder: &Path, name: &str) -> PathBuf {
+    let (stem, extension) = split_extension(name);
+    let mut path = folder.join(name);
+    let mut counter = 1;
+    while path.exists() {
+        path = folder.join(format!("{} ({}){}", stem, counter, extension));
+        counter += 1;
+    }
+    path
+}
+
+/// Folder downloads are saved to: the one from the settings, or the system's.
+fn download_folder<R: Runtime>(app: &AppHandle<R>) -> PathBuf {
+    settings::current(app)
+        .downloads
+        .folder
+        .or_else(|| app.path().download_dir().ok())
+        .or_else(|| app.path().home_dir().ok())
+        .unwrap_or_default()
+}
+
+/// Asks where to save `name`, starting in `folder`. The dialog is async since
+/// GTK's blocking one waits for the main thread, which may be the caller.
+async fn ask_destination<R: Runtime>(
+    window: &Window<R>,
+    folder: &Path,
+    name: &str,
+) -> Option<PathBuf> {
+    rfd::AsyncFileDialog::new()
+        .set_title("Save attachment")
+        .set_directory(folder)
+        .set_file_name(name)
+        .set_parent(window)
+        .save_file()
+        .await
+        .map(|file| file.path().to_path_buf())
+}
+
+/// Shows the overall progress of the downloads on the taskbar or dock icon.
+fn update_progress_bar<R: Runtime>(app: &AppHandle<R>, store: &DownloadStore, label: &str) {
+    let Some(window) = app.get_window(label) else {
+        return;
+    };
+
+    let downloads = store.downloads.lock().unwrap();
+    let active: Vec<&Download> = downloads
+        .iter()
+        .filter(|download| download.state == DownloadState::Downloading)
+        .collect();
+
+    let state = if active.is_empty() {
+        ProgressBarState {
+            status: Some(ProgressBarStatus::None),
+            progress: None,
+        }
+    } else if active.iter().all(|download| download.total.is_some()) {
+        let received: u64 = active.iter().map(|download| download.received).sum();
+        let total: u64 = active.iter().filter_map(|download| download.total).sum();
+        ProgressBarState {
+            status: Some(ProgressBarStatus::Normal),
+            progress: Some(received * 100 / total.max(1)),
+        }
+    } else {
+        ProgressBarState {
+            status: Some(ProgressBarStatus::Indeterminate),
+            progress: None,
+        }
+    };
+    let _ = window.set_progress_bar(state);
+}
+
+fn notify<R: Runtime>(app: &AppHandle<R>, download: &Download) {
+    let title = match download.state {
+        DownloadState::Finished => "Download complete",
+        _ => "Download failed",
+    };
+    let result = app
+        .notification()
+        .builder()
+        .title(title)
+        .body(&download.file_name)
+        .show();
+
+    if let Err(e) = result {
+        log::warn!("Failed to show the download notification: {}", e);
+    }
+}
+
+/// Saves downloads of Messenger webviews to the download folder, or where the
+/// user picks when the settings ask for it, and keeps the list up to date.
+/// The destination has to be known before this returns, so downloads to ask
+/// about go to a temporary file in the download folder until the user picks
+/// a place, see [`Destination`].
+pub fn on_download<R: Runtime>(webview: Webview<R>, event: DownloadEvent<'_>) -> bool {
+    let app = webview.app_handle().clone();
+    let Some(store) = app.try_state::<DownloadStore>() else {
+        return true;
+    };
+    let label = webview.window().label().to_string();
+
+    match event {
+        DownloadEvent::Requested { url, destination } => {
+            let suggested = destination
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let name = sanitize_file_name(&suggested);
+            let folder = download_folder(&app);
+
+            if let Err(e) = std::fs::create_dir_all(&folder) {
+                log::warn!("Could not create {}: {}", folder.display(), e);
+            }
+
+            if settings::current(&app).downloads.ask {
+                let path = unique_path(&folder, &format!("{}.part", name));
+                log::info!("Downloading {} to {} until asked", url, path.display());
+                *destination = path.clone();
+                let download =
+                    store.start(&url, path, &label, Destination::Asking { finished: None });
+                let _ = app.emit("downloads-changed", store.list());
+
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    choose_destination(&app, download.id, &folder, &name, &label).await;
+                });
+                return true;
+            }
+
+            let path = unique_path(&folder, &name);
+            log::info!("Downloading {} to {}", url, path.display());
+            *destination = path.clone();
+            store.start(&url, path, &label, Destination::Final);
+            let _ = app.emit("downloads-changed", store.list());
+            true
+        }
+        DownloadEvent::Finished { url, path, success } => {
+            if let Some(download) = store.finish(&url, path, success) {
+                log::info!("Download of {} ended: {:?}", url, download.state);
+                notify(&app, &download);
+            }
+            update_progress_bar(&app, &store, &label);
+            let _ = app.emit("downloads-changed", store.list());
+            true
+        }
+        _ => true,
+    }
+}
+
+/// Asks where the download `id`, started by [`on_download`], goes. It's moved
+/// there right away if the webview is already done with it.
+async fn choose_destination<R: Runtime>(
+    app: &AppHandle<R>,
+    id: u32,
+    folder: &Path,
+    name: &str,
+    label: &str,
+) {
+    let target = match app.get_window(label) {
+        Some(window) => ask_destination(&window, folder, name).await,
+        None => None,
+    };
+    if target.is_none() {
+        log::info!("Download of {} cancelled", name);
+    }
+
+    let store = app.state::<DownloadStore>();
+    if let Some(download) = store.choose(id, target) {
+        log::info!("Download of {} ended: {:?}", download.url, download.state);
+        notify(app, &download);
+    }
+    update_progress_bar(app, &store, label);
+    let _ = app.emit("downloads-changed", store.list());
+}
+
+/// Body of `url`, fetched without the cookies of any account. Enough for the
+/// signed URLs of Messenger's media.
+pub async fn fetch(url: &Url) -> Result<Vec<u8>, String> {
+    let response = reqwest::get(url.clone())
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?;
+    let bytes = response.bytes().await.map_err(|e| e.to_string())?;
+    Ok(bytes.to_vec())
+}
+
+/// Streams the body of `url` into `path` like [`fetch`], reporting the
+/// progress like a download of a webview.
+async fn fetch_to_file<R: Runtime>(
+    app: &AppHandle<R>,
+    url: &Url,
+    path: &Path,
+) -> Result<(), String> {
+    let mut response = reqwest::get(url.clone())
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?;
+    let total = response.content_length();
+
+    let mut file = std::fs::File::create(path).map_err(|e| e.to_string())?;
+    let mut received = 0;
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        file.write_all(&chunk).map_err(|e| e.to_string())?;
+        received += chunk.len() as u64;
+        progress(app, url.as_str(), received, total);
+    }
+    Ok(())
+}
+
+/// Downloads `url` outside of a webview, for the window labeled `label`, the
+/// same way as a download of the page: to the download folder or where the
+/// user picks, listed with the others.
+pub async fn save_url<R: Runtime>(
+    app: &AppHandle<R>,
+    url: Url,
+    label: &str,
+) -> Result<Option<PathBuf>, String> {
+    if url.scheme() != "https" {
+        return Err(format!("Can't download {}", url));
+    }
+    let suggested = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .unwrap_or_default()
+        .to_string();
+    let name = sanitize_file_name(&suggested);
+
+    let store = app.state::<DownloadStore>();
+    let folder = download_folder(app);
+
+    let path = if settings::current(app).downloads.ask {
+        let window = app.get_window(label).ok_or("The window was closed")?;
+        let Some(path) = ask_destination(&window, &folder, &name).await else {
+            log::info!("Download of {} cancelled", name);
+            return Ok(None);
+        };
+        path
+    } else {
+        std::fs::create_dir_all(&folder).map_err(|e| e.to_string())?;
+        unique_path(&folder, &name)
+    };
+
+    log::info!("Saving {} to {}", url, path.display());
+    store.start(&url, path.clone(), label, Destination::Final);
+    let _ = app.emit("downloads-changed", store.list());
+
+    let result = fetch_to_file(app, &url, &path).await;
+    if result.is_err() {
+        let _ = std::fs::remove_file(&path);
+    }
+
+    if let Some(download) = store.finish(&url, Some(path.clone()), result.is_ok()) {
+        notify(app, &download);
+    }
+    update_progress_bar(app, &store, label);
+    let _ = app.emit("downloads-changed", store.list());
+    result.map(|_| Some(path))
+}
+
+/// Records progress reported by the platform hooks below and by
+/// [`fetch_to_file`].
+fn progress<R: Runtime>(app: &AppHandle<R>, url: &str, received: u64, total: Option<u64>) {
+    let Some(store) = app.try_state::<DownloadStore>() else {
+        return;
+    };
+    if let Some(download) = store.progress(url, received, total) {
+        update_progress_bar(app, &store, &download.window);
+        let _ = app.emit(
+            "download-progress",
+            Progress {
+                id: download.id,
+                received,
+                total: download.total,
+            },
+        );
+    }
+}
+
+#[cfg(target_os = "linux")]
+thread_local! {
+    /// Web contexts with the progress hook, shared by the webviews of an account.
+    static HOOKED_CONTEXTS: std::cell::RefCell<std::collections::HashSet<usize>> =
+        std::cell::RefCell::new(std::collections::HashSet::new());
+}
+
+/// Follows the progress of the downloads of a webview, which Tauri doesn't
+/// report. macOS only tells when they end.
+#[cfg(target_os = "linux")]
+pub fn attach<R: Runtime>(webview: &Webview<R>) {
+    use webkit2gtk::glib::prelude::*;
+    use webkit2gtk::{DownloadExt, URIRequestExt, URIResponseExt, WebContextExt, WebViewExt};
+
+    let app = webview.app_handle().clone();
+
+    let result = webview.with_webview(move |platform| {
+        let Some(context) = platform.inner().context() else {
+            return;
+        };
+        let key = context.as_ptr() as usize;
+        if !HOOKED_CONTEXTS.with(|hooked| hooked.borrow_mut().insert(key)) {
+            return;
+        }
+
+        context.connect_download_started(move |_, download| {
+            let app = app.clone();
+            download.connect_received_data(move |download, _| {
+                let Some(url) = download.request().and_then(|request| request.uri()) else {
+                    return;
+                };
+                let total = download
+                    .response()
+                    .map(|response| response.content_length())
+                    .filter(|length| *length > 0);
+                progress(&app, &url, download.received_data_length(), total);
+            });
+        });
+    });
+
+    if let Err(e) = result {
+        log::warn!("Failed to follow the download progress: {}", e);
+    }
+}
+
+#[cfg(windows)]
+pub fn attach<R: Runtime>(webview: &Webview<R>) {
+    use webview2_com::Microsoft::Web::WebView2::Win32::{
+        ICoreWebView2DownloadOperation, ICoreWebView2_4,
+    };
+    use webview2_com::{BytesReceivedChangedEventHandler, DownloadStartingEventHandler};
+    use windows::core::{Interface, PWSTR};
+
+    let app = webview.app_handle().clone();
+
+    let result = webview.with_webview(move |platform| {
+        let handler = DownloadStartingEventHandler::create(Box::new(move |_, args| {
+            let Some(args) = args else {
+                return Ok(());
+            };
+            // SAFETY: WebView2 keeps `args` alive while the handler runs
+            let operation = unsafe { args.DownloadOperation()? };
+
+            let app = app.clone();
+            let on_bytes = BytesReceivedChangedEventHandler::create(Box::new(
+                move |operation: Option<ICoreWebView2DownloadOperation>, _| {
+                    let Some(operation) = operation else {
+                        return Ok(());
+                    };
+                    let mut uri = PWSTR::null();
+                    let mut received = 0;
+                    let mut total = 0;
+                    // SAFETY: the operation outlives its own event handlers
+                    unsafe {
+                        operation.Uri(&mut uri)?;
+                        operation.BytesReceived(&mut received)?;
+                        operation.TotalBytesToReceive(&mut total)?;
+                    }
+                    let url = webview2_com::take_pwstr(uri);
+                    let total = u64::try_from(total).ok().filter(|total| *total > 0);
+                    progress(&app, &url, received.max(0) as u64, total);
+                    Ok(())
+                },
+            ));
+
+            let mut token = 0;
+            // SAFETY: as above
+            unsafe {
+                operation.add_BytesReceivedChanged(&on_bytes, &mut token)?;
+            }
+            Ok(())
+        }));
+
+        let mut token = 0;
+        // SAFETY: the controller belongs to this webview and outlives the handler
+        unsafe {
+            if let Ok(core) = platform
+                .controller()
+                .CoreWebView2()
+                .and_then(|core| core.cast::<ICoreWebView2_4>())
+            {
+                let _ = core.add_DownloadStarting(&handler, &mut token);
+            }
+        }
+    });
+
+    if let Err(e) = result {
+        log::warn!("Failed to follow the download progress: {}", e);
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+pub fn attach<R: Runtime>(_webview: &Webview<R>) {}
+
+// =========================================================================
+//                               COMMANDS
+// =========================================================================
+
+#[tauri::command]
+pub fn list_downloads(store: tauri::State<'_, DownloadStore>) -> Vec<Download> {
+    store.list()
+}
+
+/// Where downloads go right now, for the settings.
+#[tauri::command]
+pub fn get_download_folder<R: Runtime>(app: AppHandle<R>) -> PathBuf {
+    download_folder(&app)
+}
+
+/// Asks for a new download folder and saves it in the settings.
+#[tauri::command]
+pub async fn choose_download_folder<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+) -> Option<PathBuf> {
+    let folder = rfd::AsyncFileDialog::new()
+        .set_title("Download folder")
+        .set_directory(download_folder(&app))
+        .set_parent(&window)
+        .pick_folder()
+        .await?
+        .path()
+        .to_path_buf();
+
+    let settings_store = app.state::<SettingsStore>();
+    let mut settings = settings_store.get();
+    settings.downloads.folder = Some(folder.clone());
+    settings::apply(&app, &settings_store, settings);
+    Some(folder)
+}
+
+/// Opens a finished download with its default app, once the user confirms in
+/// a native dialog. The downloads panel lives in Messenger's pages, which
+/// shouldn't open the files they download by themselves.
+#[tauri::command]
+pub async fn open_download<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    store: tauri::State<'_, DownloadStore>,
+    id: u32,
+) -> Result<(), String> {
+    let download = store.get(id).ok_or("Unknown download")?;
+    if download.state != DownloadState::Finished || !download.path.exists() {
+        return Err("The file was moved or deleted".into());
+    }
+
+    let answer = rfd::AsyncMessageDialog::new()
+        .set_level(rfd::MessageLevel::Info)
+        .set_title("Messterial")
+        .set_description(format!("Open {}?", download.file_name))
+        .set_buttons(rfd::MessageButtons::OkCancelCustom(
+            OPEN.into(),
+            CANCEL.into(),
+        ))
+        .set_parent(&window)
+        .show()
+        .await;
+    // Some platforms answer with the standard buttons even when they are renamed
+    let confirmed = match answer {
+        rfd::MessageDialogResult::Ok => true,
+        rfd::MessageDialogResult::Custom(button) => button == OPEN,
+        _ => false,
+    };
+    if !confirmed {
+        return Ok(());
+    }
+
+    app.opener()
+        .open_path(download.path.to_string_lossy(), None::<&str>)
+        .map_err(|e| e.to_string())
+}
+
+/// Shows the file of a download in the file manager.
+#[tauri::command]
+pub fn reveal_download<R: Runtime>(
+    app: AppHandle<R>,
+    store: tauri::State<'_, DownloadStore>,
+    id: u32,
+) -> Result<(), String> {
+    let download = store.get(id).ok_or("Unknown download")?;
+    if !download.path.exists() {
+        return Err("The file was moved or deleted".into());
+    }
+    app.opener()
+        .reveal_item_in_dir(&download.path)
+        .map_err(|e| e.to_string())
+}
+
+/// Forgets every download that isn't running anymore. The files stay.
+#[tauri::command]
+pub fn clear_downloads<R: Runtime>(app: AppHandle<R>, store: tauri::State<'_, DownloadStore>) {
+    let list = {
+        let mut downloads = store.downloads.lock().unwrap();
+        downloads.retain(|download| download.state == DownloadState::Downloading);
+        store.save(&downloads);
+        downloads.clone()
+    };
+    let _ = app.emit("downloads-changed", list);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_replaces_separators_and_reserved_characters() {
+        assert_eq!(sanitize_file_name("a/b\\c:d*?.jpg"), "a_b_c_d__.jpg");
+        assert_eq!(sanitize_file_name("tab\there.txt"), "tab_here.txt");
+    }
+
+    #[test]
+    fn sanitize_trims_dots_and_spaces() {
+        assert_eq!(sanitize_file_name("  ..hidden.txt. . "), "hidden.txt");
+        assert_eq!(sanitize_file_name("name  .pdf"), "name.pdf");
+    }
+
+    #[test]
+    fn sanitize_falls_back_when_nothing_is_left() {
+        assert_eq!(sanitize_file_name(""), FALLBACK_FILE_NAME);
+        assert_eq!(sanitize_file_name(" . . "), FALLBACK_FILE_NAME);
+        assert_eq!(sanitize_file_name(". .jpg"), "download.jpg");
+    }
+
+    #[test]
+    fn sanitize_prefixes_device_names() {
+        assert_eq!(sanitize_file_name("con"), "_con");
+        assert_eq!(sanitize_file_name("LPT1.tar.gz"), "_LPT1.tar.gz");
+        assert_eq!(sanitize_file_name("console.log"), "console.log");
+    }
+
+    #[test]
+    fn sanitize_keeps_the_extension_when_cutting() {
+        let name = sanitize_file_name(&format!("{}.jpg", "a".repeat(300)));
+        assert_eq!(name.len(), MAX_FILE_NAME_BYTES);
+        assert!(name.ends_with("a.jpg"));
+    }
+
+    #[test]
+    fn sanitize_cuts_on_char_boundaries() {
+        let name = sanitize_file_name(&format!("{}.png", "ż".repeat(150)));
+        assert!(name.len() <= MAX_FILE_NAME_BYTES);
+        assert!(name.ends_with("ż.png"));
+    }
+
+    #[test]
+    fn sanitize_trims_what_cutting_leaves_at_the_end() {
+        let long = format!(
+            "{} {}.jpg",
+            "a".repeat(MAX_FILE_NAME_BYTES - 5),
+            "b".repeat(50)
+        );
+        assert_eq!(
+            sanitize_file_name(&long),
+            format!("{}.jpg", "a".repeat(MAX_FILE_NAME_BYTES - 5))
+        );
+    }
+
+    #[test]
+    fn sanitize_treats_long_suffixes_as_part_of_the_name() {
+        let name = sanitize_file_name(&format!("x.{}", "e".repeat(400)));
+        assert_eq!(name.len(), MAX_FILE_NAME_BYTES);
+        assert!(name.starts_with("x.e"));
+    }
+
+    /// A store in a fresh folder, with one download asked about in progress.
+    fn asking_store(test: &str) -> (DownloadStore, PathBuf, Url) {
+        let folder = std::env::temp_dir().join(format!("messterial-{}-{}", test, now()));
+        std::fs::create_dir_all(&folder).unwrap();
+        let store = DownloadStore {
+            next_id: AtomicU32::new(0),
+            downloads: Mutex::new(Vec::new()),
+            path: folder.join(DOWNLOADS_FILE),
+        };
+        let url: Url = "https://scontent.xx.fbcdn.net/a.jpg".parse().unwrap();
+        let part = folder.join("a.jpg.part");
+        std::fs::write(&part, "data").unwrap();
+        store.start(&url, part, "main", Destination::Asking { finished: None });
+        (store, folder, url)
+    }
+
+    #[test]
+    fn asked_download_moves_when_picked_before_it_ends() {
+        let (store, folder, url) = asking_store("picked-first");
+        let target = folder.join("b.jpg");
+
+        assert!(store.choose(0, Some(target.clone())).is_none());
+        let download = store.finish(&url, None, true).unwrap();
+
+        assert_eq!(download.state, DownloadState::Finished);
+        assert_eq!(download.file_name, "b.jpg");
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "data");
+        assert!(!folder.join("a.jpg.part").exists());
+        let _ = std::fs::remove_dir_all(folder);
+    }
+
+    #[test]
+    fn asked_download_moves_when_picked_after_it_ends() {
+        let (store, folder, url) = asking_store("picked-last");
+        let target = folder.join("b.jpg");
+
+        assert!(store.finish(&url, None, true).is_none());
+        let download = store.choose(0, Some(target.clone())).unwrap();
+
+        assert_eq!(download.state, DownloadState::Finished);
+        assert_eq!(download.path, target);
+        assert!(target.exists());
+        let _ = std::fs::remove_dir_all(folder);
+    }
+
+    #[test]
+    fn cancelled_download_is_removed_with_its_file() {
+        let (store, folder, url) = asking_store("cancelled");
+
+        assert!(store.finish(&url, None, true).is_none());
+        assert!(store.choose(0, None).is_none());
+
+        assert!(store.list().is_empty());
+        assert!(!folder.join("a.jpg.part").exists());
+        let _ = std::fs::remove_dir_all(folder);
+    }
+}
@@ -0,0 +1,12 @@
+// Block Analytics/Logging
+const blockAnalytics = () => {
+    const originalFetch = window.fetch;
+    window.fetch = async (...args) => {
+        const url = args[0] ? args[0].toString() : '';
+        if (url.includes('/logging') || url.includes('/falco') || url.includes('analytics')) {
+            return new Response();
+        }
+        return originalFetch(...args);
+    };
+};
+blockAnalytics();
@@ -0,0 +1,13 @@
+// Runs a callback once the Tauri globals are injected into the page
+const whenTauriReady = (callback) => {
+    if (window.__TAURI__) {
+        callback();
+        return;
+    }
+    const tauriInterval = setInterval(() => {
+        if (window.__TAURI__) {
+            clearInterval(tauriInterval);
+            callback();
+        }
+    }, 100);
+};
@@ -0,0 +1,69 @@
+// Call window titlebar: mute, keep on top, picture in picture and hang up
+const setupCallWindow = () => {
+    const invoke = window.__TAURI__.core.invoke;
+    const appWindow = window.__TAURI__.window.getCurrentWindow();
+
+    // Messenger's own call buttons, by their label in English or Polish
+    const micOn = ['Mute', 'Turn off microphone', 'Wycisz', 'Wyłącz mikrofon'];
+    const micOff = ['Unmute', 'Turn on microphone', 'Wyłącz wyciszenie', 'Włącz mikrofon'];
+    const endCall = ['End call', 'Leave call', 'Zakończ połączenie', 'Opuść połączenie'];
+
+    const findButton = (labels) => {
+        for (const label of labels) {
+            const button = document.querySelector(`div[role="button"][aria-label="${label}"], button[aria-label="${label}"]`);
+            if (button) return button;
+        }
+        return null;
+    };
+
+    const title = document.querySelector('#call-titlebar .call-title');
+    const mute = document.getElementById('call-mute');
+    const pin = document.getElementById('call-pin');
+    const pip = document.getElementById('call-pip');
+
+    const setPressed = (button, pressed) => button.setAttribute('aria-pressed', pressed);
+
+    // Follows the page, since Messenger has its own mute button too
+    const syncMute = () => {
+        const muted = !findButton(micOn) && !!findButton(micOff);
+        setPressed(mute, muted);
+        const label = muted ? 'Unmute microphone' : 'Mute microphone';
+        mute.title = label;
+        mute.setAttribute('aria-label', label);
+    };
+    new MutationObserver(syncMute).observe(document.body, { subtree: true, attributes: true, attributeFilter: ['aria-label'] });
+    syncMute();
+
+    mute.addEventListener('click', () => {
+        (findButton(micOn) || findButton(micOff))?.click();
+    });
+
+    pin.addEventListener('click', async () => {
+        setPressed(pin, await invoke('toggle_call_always_on_top'));
+    });
+
+    pip.addEventListener('click', async () => {
+        const active = await invoke('toggle_call_pip');
+        document.documentElement.classList.toggle('call-pip', active);
+        setPressed(pip, active);
+        setPressed(pin, await appWindow.isAlwaysOnTop());
+    });
+
+    document.getElementById('call-hang-up').addEventListener('click', () => {
+        const end = findButton(endCall);
+        if (!end) {
+            appWindow.close();
+            return;
+        }
+        end.click();
+        // Messenger usually closes the window itself once the call ended
+        setTimeout(() => appWindow.close(), 1500);
+    });
+
+    const syncTitle = () => {
+        title.textContent = document.title || 'Messenger call';
+    };
+    new MutationObserver(syncTitle).observe(document.querySelector('title') || document.head, { childList: true, subtree: true });
+    syncTitle();
+};
+whenTauriReady(setupCallWindow);
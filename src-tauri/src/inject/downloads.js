@@ -0,0 +1,148 @@
+// Downloaded attachments with open and show in folder actions, see downloads.rs
+const setupDownloads = () => {
+    const invoke = window.__TAURI__.core.invoke;
+
+    const panel = document.createElement('div');
+    panel.id = 'messterial-downloads';
+    panel.innerHTML = `
+        <div class="downloads-card" role="dialog" aria-labelledby="downloads-title">
+            <div class="downloads-title" id="downloads-title">Downloads</div>
+            <div class="downloads-list" id="downloads-list"></div>
+            <div class="downloads-footer">
+                <button class="download-button" id="downloads-clear">Clear list</button>
+                <button class="download-button" id="downloads-close">Done</button>
+            </div>
+        </div>
+    `;
+    document.body.appendChild(panel);
+
+    const list = panel.querySelector('#downloads-list');
+    const button = document.getElementById('titlebar-downloads');
+    let downloads = [];
+
+    const formatSize = (bytes) => {
+        if (bytes < 1024) return `${bytes} B`;
+        const units = ['KB', 'MB', 'GB'];
+        let size = bytes / 1024;
+        let unit = 0;
+        while (size >= 1024 && unit < units.length - 1) {
+            size /= 1024;
+            unit += 1;
+        }
+        return `${size.toFixed(size < 10 ? 1 : 0)} ${units[unit]}`;
+    };
+
+    const describe = (download) => {
+        if (download.state === 'failed') return 'Failed';
+        if (download.state === 'finished') return download.total ? formatSize(download.total) : 'Done';
+        return download.total
+            ? `${formatSize(download.received)} of ${formatSize(download.total)}`
+            : formatSize(download.received);
+    };
+
+    const action = (label, command, download, status) => {
+        const element = document.createElement('button');
+        element.className = 'download-button';
+        element.textContent = label;
+        element.addEventListener('click', async () => {
+            try {
+                await invoke(command, { id: download.id });
+            } catch (err) {
+                status.textContent = err;
+            }
+        });
+        return element;
+    };
+
+    const render = () => {
+        list.innerHTML = '';
+        if (downloads.length === 0) {
+            list.innerHTML = '<div class="downloads-empty">Nothing downloaded yet</div>';
+        }
+
+        downloads.forEach((download) => {
+            const item = document.createElement('div');
+            item.className = `download-item ${download.state}`;
+            item.dataset.id = download.id;
+
+            const info = document.createElement('div');
+            info.className = 'download-info';
+            const name = document.createElement('div');
+            name.className = 'download-name';
+            name.textContent = download.file_name;
+            name.title = download.path;
+            const status = document.createElement('div');
+            status.className = 'download-status';
+            status.textContent = describe(download);
+            info.append(name, status);
+
+            if (download.state === 'downloading') {
+                const bar = document.createElement('div');
+                bar.className = 'download-progress';
+                bar.appendChild(document.createElement('div'));
+                info.appendChild(bar);
+            }
+            item.appendChild(info);
+
+            if (download.state === 'finished') {
+                item.appendChild(action('Open', 'open_download', download, status));
+                item.appendChild(action('Show in folder', 'reveal_download', download, status));
+            }
+            list.appendChild(item);
+            updateProgress(download);
+        });
+
+        if (button) {
+            button.hidden = downloads.length === 0;
+            button.classList.toggle('downloading', downloads.some((download) => download.state === 'downloading'));
+        }
+    };
+
+    const updateProgress = (download) => {
+        const item = list.querySelector(`.download-item[data-id="${download.id}"]`);
+        if (!item) return;
+        item.querySelector('.download-status').textContent = describe(download);
+        const bar = item.querySelector('.download-progress > div');
+        if (bar) bar.style.width = download.total ? `${Math.min(100, (download.received / download.total) * 100)}%` : '100%';
+    };
+
+    const close = () => panel.classList.remove('open');
+    const open = async () => {
+        downloads = await invoke('list_downloads');
+        render();
+        panel.classList.add('open');
+        panel.querySelector('#downloads-close').focus();
+    };
+
+    panel.addEventListener('click', (e) => {
+        if (e.target === panel) close();
+    });
+    panel.addEventListener('keydown', (e) => {
+        if (e.key !== 'Escape') return;
+        e.stopPropagation();
+        close();
+    });
+    panel.querySelector('#downloads-close').addEventListener('click', close);
+    panel.querySelector('#downloads-clear').addEventListener('click', () => invoke('clear_downloads'));
+    button?.addEventListener('click', () => window.__messterialOpenDownloads());
+
+    window.__TAURI__.event.listen('downloads-changed', (event) => {
+        downloads = event.payload;
+        render();
+    });
+    window.__TAURI__.event.listen('download-progress', (event) => {
+        const download = downloads.find((entry) => entry.id === event.payload.id);
+        if (!download) return;
+        download.received = event.payload.received;
+        download.total = event.payload.total;
+        updateProgress(download);
+    });
+
+    invoke('list_downloads').then((list) => {
+        downloads = list;
+        render();
+    });
+
+    window.__messterialOpenDownloads = () => (panel.classList.contains('open') ? close() : open());
+};
+whenTauriReady(setupDownloads);
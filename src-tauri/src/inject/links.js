@@ -0,0 +1,53 @@
+// External link handler
+const setupExternalLinks = () => {
+    const openExternalUrl = async (href) => {
+        if (!window.__TAURI__) return false;
+        try {
+            // Tauri 2 plugin invoke pattern
+            await window.__TAURI__.core.invoke('plugin:opener|open_url', { url: href });
+            return true;
+        } catch (err) {
+            console.error('Messterial: Failed to open URL:', err);
+            return false;
+        }
+    };
+
+    document.addEventListener('click', async (e) => {
+        const link = e.target.closest('a[href]');
+        if (!link) return;
+        
+        const href = link.getAttribute('href');
+        if (!href) return;
+        
+        // Check if it's an external link (not messenger.com)
+        const isExternal = href.startsWith('http://') || href.startsWith('https://');
+        const isMessengerInternal = href.includes('messenger.com') || href.includes('facebook.com/messages') || href.includes('facebook.com/login');
+        
+        if (isExternal && !isMessengerInternal) {
+            e.preventDefault();
+            e.stopPropagation();
+            await openExternalUrl(href);
+        }
+    }, true);
+    
+    // Also handle middle-click
+    document.addEventListener('auxclick', async (e) => {
+        if (e.button !== 1) return; // Middle click only
+        
+        const link = e.target.closest('a[href]');
+        if (!link) return;
+        
+        const href = link.getAttribute('href');
+        if (!href) return;
+        
+        const isExternal = href.startsWith('http://') || href.startsWith('https://');
+        const isMessengerInternal = href.includes('messenger.com') || href.includes('facebook.com/messages') || href.includes('facebook.com/login');
+        
+        if (isExternal && !isMessengerInternal) {
+            e.preventDefault();
+            e.stopPropagation();
+            await openExternalUrl(href);
+        }
+    }, true);
+};
+setupExternalLinks();
@@ -0,0 +1,50 @@
+// Opens photos and videos of the thread in the media viewer, see media_viewer.rs
+const setupMediaViewer = () => {
+    const invoke = window.__TAURI__.core.invoke;
+
+    // Smaller images are avatars, reactions and emoji rather than attachments
+    const MIN_SIZE = 64;
+
+    const isCdn = (url) => {
+        try {
+            const parsed = new URL(url);
+            return parsed.protocol === 'https:' && /(^|\.)fbcdn\.net$/.test(parsed.hostname);
+        } catch {
+            return false;
+        }
+    };
+
+    const isAttachment = (element) => {
+        if (element.closest('[aria-label*="sticker" i], [aria-label*="naklejk" i]')) return false;
+        const box = element.getBoundingClientRect();
+        return box.width >= MIN_SIZE && box.height >= MIN_SIZE;
+    };
+
+    // Photos and videos loaded in the open thread, oldest first. Videos
+    // streamed from a blob can't be opened anywhere else, so they stay here.
+    const threadMedia = () => Array.from(document.querySelectorAll('div[role="main"] img, div[role="main"] video'))
+        .filter(isAttachment)
+        .map((element) => ({
+            element,
+            url: element.currentSrc || element.src,
+            kind: element.tagName === 'VIDEO' ? 'video' : 'image',
+        }))
+        .filter((item) => isCdn(item.url));
+
+    // Clicking with a modifier still opens Messenger's own viewer
+    document.addEventListener('click', (e) => {
+        if (e.button !== 0 || e.ctrlKey || e.metaKey || e.shiftKey || e.altKey) return;
+        const target = e.target.closest('div[role="main"] img, div[role="main"] video');
+        if (!target || target.tagName === 'VIDEO') return;
+
+        const media = threadMedia();
+        const index = media.findIndex((item) => item.element === target);
+        if (index === -1) return;
+
+        e.preventDefault();
+        e.stopPropagation();
+        const items = media.map(({ url, kind }) => ({ url, kind }));
+        invoke('open_media_viewer', { items, index });
+    }, true);
+};
+whenTauriReady(setupMediaViewer);
@@ -0,0 +1,42 @@
+// Pops conversations out into their own windows, see popout.rs
+const setupPopout = () => {
+    const invoke = window.__TAURI__.core.invoke;
+
+    // `/t/<id>` or `/e2ee/t/<id>` of a conversation link or page, or null
+    const conversationPath = (href) => {
+        const path = new URL(href, location.href).pathname;
+        return /^\/(e2ee\/)?t\/[^/]+\/?$/.test(path) ? path : null;
+    };
+
+    // Messenger may replace the classes of the document while it loads
+    if (window.__MESSTERIAL_POPOUT__) {
+        document.documentElement.classList.add('messterial-popout');
+        return;
+    }
+
+    // Right-clicking a chat in the list offers to open it in a new window
+    document.addEventListener('contextmenu', (e) => {
+        const link = e.target.closest('div[role="navigation"] a[href*="/t/"]');
+        const path = link && conversationPath(link.href);
+        if (!path) return;
+        e.preventDefault();
+        e.stopPropagation();
+        invoke('show_conversation_menu', { path });
+    }, true);
+
+    // The titlebar button pops out the open conversation
+    const button = document.getElementById('titlebar-popout');
+    if (!button) return;
+    const update = () => {
+        button.hidden = !conversationPath(location.href);
+    };
+    update();
+    new MutationObserver(update).observe(document.querySelector('title') || document.head, { childList: true, subtree: true });
+    window.addEventListener('popstate', update);
+    button.addEventListener('click', () => {
+        const path = conversationPath(location.href);
+        if (path) invoke('pop_out_conversation', { path });
+    });
+};
+
+whenTauriReady(setupPopout);
@@ -0,0 +1,72 @@
+// Screen sharing through Messterial's own source picker, see screen_share.rs.
+// The picked source is captured natively and streamed from a canvas.
+const setupScreenShare = () => {
+    const invoke = window.__TAURI__.core.invoke;
+    const mediaDevices = navigator.mediaDevices;
+    if (!mediaDevices?.getDisplayMedia) return;
+    const platformGetDisplayMedia = mediaDevices.getDisplayMedia.bind(mediaDevices);
+    const frameRate = 15;
+
+    // Draws the frames of a native capture into a canvas stream
+    const captureStream = (id) => {
+        const canvas = document.createElement('canvas');
+        const context = canvas.getContext('2d');
+        const stream = canvas.captureStream(frameRate);
+        const [track] = stream.getVideoTracks();
+        let running = true;
+
+        const stop = () => {
+            if (!running) return;
+            running = false;
+            invoke('stop_screen_share', { id });
+        };
+
+        const draw = async () => {
+            if (!running) return;
+            try {
+                const frame = await invoke('screen_share_frame', { id });
+                if (frame.byteLength > 0) {
+                    const bitmap = await createImageBitmap(new Blob([frame], { type: 'image/jpeg' }));
+                    if (canvas.width !== bitmap.width || canvas.height !== bitmap.height) {
+                        canvas.width = bitmap.width;
+                        canvas.height = bitmap.height;
+                    }
+                    context.drawImage(bitmap, 0, 0);
+                    bitmap.close();
+                }
+            } catch (e) {
+                // The shared window was closed or the screen is gone
+                running = false;
+                track.stop();
+                track.dispatchEvent(new Event('ended'));
+                return;
+            }
+            setTimeout(draw, 1000 / frameRate);
+        };
+        draw();
+
+        const stopTrack = track.stop.bind(track);
+        track.stop = () => {
+            stop();
+            stopTrack();
+        };
+        return stream;
+    };
+
+    mediaDevices.getDisplayMedia = async (constraints) => {
+        // Resolves with a one-time token for the source picked in the app's
+        // own picker window, or null when the platform has its own picker,
+        // e.g. the ScreenCast portal on Wayland
+        let token;
+        try {
+            token = await invoke('pick_share_source');
+        } catch (e) {
+            throw new DOMException(String(e), 'NotAllowedError');
+        }
+        if (token === null) return platformGetDisplayMedia(constraints);
+
+        const id = await invoke('start_screen_share', { token });
+        return captureStream(id);
+    };
+};
+whenTauriReady(setupScreenShare);
@@ -0,0 +1,319 @@
+// Messterial settings panel
+const setupSettingsPanel = () => {
+    const invoke = window.__TAURI__.core.invoke;
+
+    // [setting path, label, [value, label] options, or null for a switch]
+    const appearance = [
+        ['theme', 'Theme', [['dark', 'Dark'], ['light', 'Light'], ['high-contrast', 'High contrast']]],
+        ['animation', 'Animations', [['system', 'Follow system'], ['full', 'Full'], ['reduced', 'Reduced'], ['none', 'None']]],
+        ['density', 'Density', [['compact', 'Compact'], ['comfortable', 'Comfortable'], ['spacious', 'Spacious']]],
+        ['scrollbars.width', 'Scrollbar width', [[4, '4 px'], [6, '6 px'], [8, '8 px'], [10, '10 px'], [12, '12 px']]],
+        ['scrollbars.color', 'Scrollbar color', [['neutral', 'Neutral'], ['accent', 'Accent']]],
+        ['scrollbars.auto_hide', 'Hide scrollbars until hovered', null],
+        ['scrollbars.overlay', 'Overlay scrollbars', null],
+    ];
+
+    const features = [
+        ['layout', 'Floating cards layout'],
+        ['titlebar', 'Custom titlebar'],
+        ['bloat', 'Remove bloat'],
+        ['bubbles', 'Round chat bubbles'],
+        ['scrollbars', 'Custom scrollbars'],
+        ['analytics', 'Block analytics and logging'],
+        ['links', 'Open external links in the browser'],
+        ['badges', 'Unread badge'],
+        ['shortcuts', 'Keyboard shortcuts'],
+        ['switcher', 'Quick switcher'],
+        ['downloads', 'Downloads panel'],
+        ['media', 'Media viewer'],
+        ['popout', 'Pop out conversations'],
+    ];
+
+    const shortcuts = [
+        ['toggle_window', 'Show or hide Messterial (global)'],
+        ['toggle_mute', 'Mute or unmute (global)'],
+        ['quick_switcher', 'Quick switcher'],
+        ['jump_to_chat', 'Jump to chat 1-9 (modifiers)'],
+        ['open_settings', 'Settings'],
+        ['hide_window', 'Hide window'],
+        ['zoom_in', 'Zoom in'],
+        ['zoom_out', 'Zoom out'],
+        ['reset_zoom', 'Reset zoom'],
+    ];
+
+    const decisions = [['ask', 'Ask'], ['allow', 'Allow'], ['deny', 'Block']];
+    const permissions = [
+        ['camera', 'Camera'],
+        ['microphone', 'Microphone'],
+        ['screen', 'Screen sharing'],
+    ];
+
+    const fontScales = [0.85, 1, 1.15, 1.3, 1.5];
+
+    const panel = document.createElement('div');
+    panel.id = 'messterial-settings';
+    panel.innerHTML = `
+        <div class="settings-card">
+            <div class="settings-title">Messterial settings</div>
+            <div class="settings-section">Appearance</div>
+            <div id="settings-appearance"></div>
+            <div class="settings-section">Features</div>
+            <div id="settings-features"></div>
+            <div class="settings-section">Shortcuts</div>
+            <div id="settings-shortcuts"></div>
+            <div class="settings-section">Permissions</div>
+            <div id="settings-permissions"></div>
+            <div class="settings-section">Downloads</div>
+            <div id="settings-downloads"></div>
+            <div class="settings-section">Windows</div>
+            <div class="settings-row">
+                Saved window sizes and positions
+                <button class="settings-button" id="settings-reset-windows">Reset</button>
+            </div>
+            <div class="settings-footer">
+                <span class="settings-notice" id="settings-notice"></span>
+                <button class="settings-button" id="settings-restart" style="display: none">Restart</button>
+                <button class="settings-button filled" id="settings-close">Done</button>
+            </div>
+        </div>
+    `;
+    document.body.appendChild(panel);
+
+    const notice = panel.querySelector('#settings-notice');
+    const restart = panel.querySelector('#settings-restart');
+    let settings = null;
+    let fontScale = 1;
+
+    const save = async () => {
+        const needsRestart = await invoke('update_settings', { settings });
+        if (needsRestart) {
+            notice.textContent = 'Some changes apply after a restart.';
+            restart.style.display = '';
+        }
+    };
+
+    // 'scrollbars.width' -> settings.scrollbars and 'width'
+    const resolve = (path) => {
+        const keys = path.split('.');
+        const last = keys.pop();
+        return [keys.reduce((object, key) => object[key], settings), last];
+    };
+
+    const settingRow = ([path, label, options]) => {
+        const [object, key] = resolve(path);
+        const row = document.createElement('label');
+        row.className = 'settings-row';
+        row.textContent = label;
+
+        if (options) {
+            const select = document.createElement('select');
+            select.className = 'settings-select';
+            options.forEach(([value, text]) => select.add(new Option(text, value)));
+            select.selectedIndex = options.findIndex(([value]) => value === object[key]);
+            select.addEventListener('change', () => {
+                object[key] = options[select.selectedIndex][0];
+                save();
+            });
+            row.appendChild(select);
+        } else {
+            const toggle = document.createElement('input');
+            toggle.type = 'checkbox';
+            toggle.checked = object[key];
+            toggle.addEventListener('change', () => {
+                object[key] = toggle.checked;
+                save();
+            });
+            row.appendChild(toggle);
+        }
+        return row;
+    };
+
+    const renderAppearance = () => {
+        const list = panel.querySelector('#settings-appearance');
+        list.innerHTML = '';
+        appearance.forEach((entry) => list.appendChild(settingRow(entry)));
+
+        // Saved per window rather than with the other settings
+        const row = document.createElement('label');
+        row.className = 'settings-row';
+        row.textContent = 'Text size (this window)';
+
+        const select = document.createElement('select');
+        select.className = 'settings-select';
+        fontScales.forEach((scale) => select.add(new Option(`${Math.round(scale * 100)}%`, scale)));
+        select.value = fontScale;
+        select.addEventListener('change', () => invoke('set_font_scale', { scale: Number(select.value) }));
+        row.appendChild(select);
+        list.appendChild(row);
+    };
+
+    // Decisions remembered from the permission prompt, Ask forgets them.
+    // Only the prompt can allow, so Allow can't be picked here.
+    const renderPermissions = () => {
+        const list = panel.querySelector('#settings-permissions');
+        list.innerHTML = '';
+        permissions.forEach(([permission, label]) => {
+            const row = document.createElement('label');
+            row.className = 'settings-row';
+            row.textContent = label;
+
+            const select = document.createElement('select');
+            select.className = 'settings-select';
+            decisions.forEach(([value, text]) => {
+                const option = new Option(text, value);
+                option.disabled = value === 'allow';
+                select.add(option);
+            });
+            select.value = settings.permissions[permission];
+            select.addEventListener('change', async () => {
+                await invoke('set_media_permission', { permission, decision: select.value });
+                settings.permissions[permission] = select.value;
+            });
+            row.appendChild(select);
+            list.appendChild(row);
+        });
+    };
+
+    // The folder is picked natively and saved by choose_download_folder
+    const renderDownloads = async () => {
+        const list = panel.querySelector('#settings-downloads');
+        list.innerHTML = '';
+
+        const folderRow = document.createElement('div');
+        folderRow.className = 'settings-row';
+        const folder = document.createElement('span');
+        folder.className = 'settings-folder';
+        folder.textContent = await invoke('get_download_folder');
+        folder.title = folder.textContent;
+        const change = document.createElement('button');
+        change.className = 'settings-button';
+        change.textContent = 'Change';
+        change.addEventListener('click', async () => {
+            const chosen = await invoke('choose_download_folder');
+            if (!chosen) return;
+            settings.downloads.folder = chosen;
+            folder.textContent = chosen;
+            folder.title = chosen;
+        });
+        folderRow.append('Save to', folder, change);
+        list.appendChild(folderRow);
+
+        list.appendChild(settingRow(['downloads.ask', 'Ask where to save each file', null]));
+
+        const listRow = document.createElement('div');
+        listRow.className = 'settings-row';
+        listRow.textContent = 'Downloaded files';
+        const show = document.createElement('button');
+        show.className = 'settings-button';
+        show.textContent = 'Show';
+        show.addEventListener('click', () => {
+            close();
+            window.__messterialOpenDownloads?.();
+        });
+        listRow.appendChild(show);
+        list.appendChild(listRow);
+    };
+
+    const render = () => {
+        const list = panel.querySelector('#settings-features');
+        list.innerHTML = '';
+        features.forEach(([key, label]) => {
+            const row = document.createElement('label');
+            row.className = 'settings-row';
+            row.textContent = label;
+
+            const toggle = document.createElement('input');
+            toggle.type = 'checkbox';
+            toggle.checked = settings.features[key];
+            toggle.addEventListener('change', () => {
+                settings.features[key] = toggle.checked;
+                save();
+            });
+            row.appendChild(toggle);
+            list.appendChild(row);
+        });
+    };
+
+    // Records the next key combination, Backspace clears the shortcut
+    const renderShortcuts = () => {
+        const list = panel.querySelector('#settings-shortcuts');
+        list.innerHTML = '';
+
+        // The recorder comes with shortcuts.js, which is only loaded when enabled
+        if (!window.__messterialShortcuts) {
+            const row = document.createElement('div');
+            row.className = 'settings-row';
+            row.textContent = settings.features.shortcuts
+                ? 'Shortcuts can be changed after a restart.'
+                : 'Keyboard shortcuts are turned off.';
+            list.appendChild(row);
+            return;
+        }
+
+        shortcuts.forEach(([key, label]) => {
+            const row = document.createElement('label');
+            row.className = 'settings-row';
+            row.textContent = label;
+
+            const input = document.createElement('input');
+            input.className = 'settings-shortcut';
+            input.readOnly = true;
+            input.value = settings.shortcuts[key];
+            input.placeholder = 'None';
+            input.addEventListener('keydown', (e) => {
+                e.preventDefault();
+                e.stopPropagation();
+                if (e.key === 'Tab') return;
+
+                let value = null;
+                if (e.key === 'Backspace' || e.key === 'Delete') {
+                    value = '';
+                } else {
+                    const { modifiers, key: pressed } = window.__messterialShortcuts.fromEvent(e);
+                    if (key === 'jump_to_chat') {
+                        if (modifiers && /^[1-9]$/.test(pressed)) value = modifiers;
+                    } else if (pressed) {
+                        value = modifiers ? `${modifiers}+${pressed}` : pressed;
+                    }
+                }
+
+                if (value !== null) {
+                    input.value = value;
+                    settings.shortcuts[key] = value;
+                    save();
+                }
+            });
+            row.appendChild(input);
+            list.appendChild(row);
+        });
+    };
+
+    const open = async () => {
+        settings = await invoke('get_settings');
+        fontScale = (await invoke('get_window_zoom')).font_scale;
+        renderAppearance();
+        render();
+        renderShortcuts();
+        renderPermissions();
+        renderDownloads();
+        panel.classList.add('open');
+    };
+    const close = () => panel.classList.remove('open');
+
+    panel.addEventListener('click', (e) => {
+        if (e.target === panel) close();
+    });
+    panel.querySelector('#settings-close').addEventListener('click', close);
+    restart.addEventListener('click', () => invoke('restart_app'));
+    panel.querySelector('#settings-reset-windows').addEventListener('click', () => {
+        if (!confirm('Messterial will restart and open every window at its default size.')) return;
+        invoke('reset_window_state');
+    });
+
+    document.addEventListener('keydown', (e) => {
+        if (e.key === 'Escape' && panel.classList.contains('open')) close();
+    });
+
+    window.__messterialOpenSettings = () => (panel.classList.contains('open') ? close() : open());
+};
+whenTauriReady(setupSettingsPanel);
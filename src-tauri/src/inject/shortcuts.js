@@ -0,0 +1,115 @@
+// Keyboard shortcuts from the keymap in the settings
+const setupShortcuts = async () => {
+    const invoke = window.__TAURI__.core.invoke;
+    const listen = window.__TAURI__.event.listen;
+    const isMac = navigator.platform.toUpperCase().includes('MAC');
+
+    let keymap = (await invoke('get_settings')).shortcuts;
+    listen('settings-changed', (event) => { keymap = event.payload.shortcuts; });
+
+    // "CmdOrCtrl+Shift+K" -> modifiers and a KeyboardEvent.code
+    const parse = (accelerator) => {
+        const binding = { ctrl: false, alt: false, shift: false, meta: false, code: null };
+        accelerator.split('+').forEach((token) => {
+            switch (token.toLowerCase()) {
+                case 'cmdorctrl':
+                case 'commandorcontrol':
+                    binding[isMac ? 'meta' : 'ctrl'] = true; break;
+                case 'ctrl':
+                case 'control':
+                    binding.ctrl = true; break;
+                case 'alt':
+                case 'option':
+                    binding.alt = true; break;
+                case 'shift':
+                    binding.shift = true; break;
+                case 'cmd':
+                case 'command':
+                case 'super':
+                case 'meta':
+                    binding.meta = true; break;
+                default:
+                    if (/^[a-z]$/i.test(token)) binding.code = 'Key' + token.toUpperCase();
+                    else if (/^[0-9]$/.test(token)) binding.code = 'Digit' + token;
+                    else binding.code = { ',': 'Comma', '=': 'Equal', '-': 'Minus' }[token] || token;
+            }
+        });
+        return binding;
+    };
+
+    const modifiersMatch = (binding, e) => binding.ctrl === e.ctrlKey && binding.alt === e.altKey
+        && binding.shift === e.shiftKey && binding.meta === e.metaKey;
+
+    const matches = (accelerator, e) => {
+        if (!accelerator) return false;
+        const binding = parse(accelerator);
+        return binding.code === e.code && modifiersMatch(binding, e);
+    };
+
+    // Builds an accelerator from a key press, for the settings panel
+    const fromEvent = (e) => {
+        const tokens = [];
+        if (isMac ? e.metaKey : e.ctrlKey) tokens.push('CmdOrCtrl');
+        if (isMac && e.ctrlKey) tokens.push('Ctrl');
+        if (!isMac && e.metaKey) tokens.push('Super');
+        if (e.altKey) tokens.push('Alt');
+        if (e.shiftKey) tokens.push('Shift');
+        if (['Control', 'Alt', 'Shift', 'Meta'].includes(e.key)) return { modifiers: tokens.join('+'), key: null };
+
+        const key = e.code.replace(/^Key/, '').replace(/^Digit/, '');
+        return { modifiers: tokens.join('+'), key };
+    };
+    window.__messterialShortcuts = { matches, fromEvent };
+
+    const jumpToChat = (index) => {
+        const rows = document.querySelectorAll('div[role="navigation"] div[role="row"]');
+        const row = rows[index];
+        if (!row) return;
+        (row.querySelector('a') || row).click();
+    };
+
+    const openQuickSwitcher = () => {
+        if (window.__messterialOpenSwitcher) {
+            window.__messterialOpenSwitcher();
+            return;
+        }
+        const search = document.querySelector('div[role="navigation"] input[type="search"]');
+        if (search) search.focus();
+    };
+
+    document.addEventListener('keydown', (e) => {
+        // Being recorded in the settings panel
+        if (e.target.classList?.contains('settings-shortcut')) return;
+
+        let handled = true;
+        if (matches(keymap.quick_switcher, e)) openQuickSwitcher();
+        else if (matches(keymap.open_settings, e)) window.__messterialOpenSettings?.();
+        else if (matches(keymap.hide_window, e)) invoke('hide_window');
+        else if (matches(keymap.zoom_in, e)) invoke('zoom', { direction: 'in' });
+        else if (matches(keymap.zoom_out, e)) invoke('zoom', { direction: 'out' });
+        else if (matches(keymap.reset_zoom, e)) invoke('zoom', { direction: 'reset' });
+        else if (keymap.jump_to_chat && /^Digit[1-9]$/.test(e.code)
+            && modifiersMatch(parse(keymap.jump_to_chat), e)) jumpToChat(Number(e.code.slice(5)) - 1);
+        else handled = false;
+
+        if (handled) {
+            e.preventDefault();
+            e.stopPropagation();
+        }
+    }, true);
+
+    // Global mute toggle: silence every media element, including ones created later
+    let muted = await invoke('is_muted');
+    const applyMute = () => document.querySelectorAll('audio, video').forEach((media) => { media.muted = muted; });
+    const play = HTMLMediaElement.prototype.play;
+    HTMLMediaElement.prototype.play = function (...args) {
+        if (muted) this.muted = true;
+        return play.apply(this, args);
+    };
+    listen('toggle-mute', (event) => {
+        muted = event.payload;
+        applyMute();
+    });
+    applyMute();
+};
+whenTauriReady(setupShortcuts);
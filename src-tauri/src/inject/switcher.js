@@ -0,0 +1,138 @@
+// Quick switcher over the chat list and app commands
+const setupQuickSwitcher = () => {
+    const invoke = window.__TAURI__.core.invoke;
+
+    const commands = [
+        { key: 'command:settings', label: 'Open settings', run: () => window.__messterialOpenSettings?.() },
+        { key: 'command:theme', label: 'Toggle theme', run: () => invoke('toggle_theme') },
+        { key: 'command:updates', label: 'Check for updates', run: () => invoke('check_for_updates') },
+    ];
+
+    const overlay = document.createElement('div');
+    overlay.id = 'messterial-switcher';
+    overlay.innerHTML = `
+        <div class="switcher-card">
+            <input class="switcher-input" placeholder="Search chats and commands" spellcheck="false">
+            <div class="switcher-results"></div>
+        </div>
+    `;
+    document.body.appendChild(overlay);
+
+    const input = overlay.querySelector('.switcher-input');
+    const results = overlay.querySelector('.switcher-results');
+    let entries = [];
+    let matches = [];
+    let selected = 0;
+
+    // Chats currently rendered in the navigation card
+    const scrapeChats = () => Array.from(document.querySelectorAll('div[role="navigation"] div[role="row"]'))
+        .map((row) => {
+            const link = row.querySelector('a[href*="/t/"]');
+            if (!link) return null;
+            const label = (row.innerText || '').split('\n').find((line) => line.trim()) || '';
+            return { key: new URL(link.href).pathname, label: label.trim(), row: link };
+        })
+        .filter((chat) => chat && chat.label);
+
+    // Subsequence match, rewarding consecutive letters and word starts
+    const fuzzyScore = (query, text) => {
+        if (!query) return 0;
+        const haystack = text.toLowerCase();
+        let score = 0;
+        let position = 0;
+        let streak = 0;
+        for (const char of query.toLowerCase()) {
+            const index = haystack.indexOf(char, position);
+            if (index === -1) return null;
+            streak = index === position ? streak + 1 : 0;
+            score += 1 + streak * 2 + (index === 0 || haystack[index - 1] === ' ' ? 3 : 0);
+            position = index + 1;
+        }
+        return score;
+    };
+
+    const render = () => {
+        const query = input.value.trim();
+        matches = entries
+            .map((entry) => ({ entry, score: fuzzyScore(query, entry.label) }))
+            .filter(({ score }) => score !== null)
+            .sort((a, b) => (b.score + Math.log1p(b.entry.rank) * 4) - (a.score + Math.log1p(a.entry.rank) * 4))
+            .map(({ entry }) => entry);
+        selected = Math.min(selected, Math.max(matches.length - 1, 0));
+
+        results.innerHTML = '';
+        if (matches.length === 0) {
+            results.innerHTML = '<div class="switcher-empty">No matches</div>';
+            return;
+        }
+        matches.forEach((entry, index) => {
+            const item = document.createElement('div');
+            item.className = 'switcher-item' + (index === selected ? ' selected' : '');
+            item.textContent = entry.label;
+
+            const kind = document.createElement('span');
+            kind.className = 'switcher-kind';
+            kind.textContent = entry.run ? 'Command' : 'Chat';
+            item.appendChild(kind);
+
+            item.addEventListener('click', () => pick(entry));
+            results.appendChild(item);
+        });
+        results.children[selected]?.scrollIntoView({ block: 'nearest' });
+    };
+
+    const close = () => overlay.classList.remove('open');
+
+    const pick = (entry) => {
+        close();
+        invoke('record_switcher_pick', { key: entry.key, label: entry.label });
+        if (entry.run) {
+            entry.run();
+        } else if (entry.row) {
+            entry.row.click();
+        } else {
+            // Picked before but not rendered in the chat list right now
+            window.location.assign(entry.key);
+        }
+    };
+
+    const open = async () => {
+        const ranking = await invoke('switcher_ranking');
+        const ranks = new Map(ranking.map((ranked) => [ranked.key, ranked]));
+
+        const chats = scrapeChats();
+        const known = new Set(chats.map((chat) => chat.key));
+        const remembered = ranking
+            .filter((ranked) => ranked.key.startsWith('/') && !known.has(ranked.key))
+            .map((ranked) => ({ key: ranked.key, label: ranked.label }));
+
+        entries = [...chats, ...remembered, ...commands]
+            .map((entry) => ({ ...entry, rank: ranks.get(entry.key)?.score || 0 }));
+
+        input.value = '';
+        selected = 0;
+        render();
+        overlay.classList.add('open');
+        input.focus();
+    };
+
+    input.addEventListener('input', () => {
+        selected = 0;
+        render();
+    });
+    input.addEventListener('keydown', (e) => {
+        if (e.key === 'ArrowDown') selected = Math.min(selected + 1, matches.length - 1);
+        else if (e.key === 'ArrowUp') selected = Math.max(selected - 1, 0);
+        else if (e.key === 'Enter' && matches[selected]) pick(matches[selected]);
+        else if (e.key === 'Escape') close();
+        else return;
+        e.preventDefault();
+        if (overlay.classList.contains('open')) render();
+    });
+    overlay.addEventListener('click', (e) => {
+        if (e.target === overlay) close();
+    });
+
+    window.__messterialOpenSwitcher = () => (overlay.classList.contains('open') ? close() : open());
+};
+whenTauriReady(setupQuickSwitcher);
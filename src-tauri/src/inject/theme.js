@@ -0,0 +1,12 @@
+// Keeps Messenger's own color scheme in line with the Messterial theme
+const setupTheme = async () => {
+    const apply = (theme) => {
+        const root = document.documentElement;
+        root.classList.toggle('__fb-dark-mode', theme !== 'light');
+        root.classList.toggle('__fb-light-mode', theme === 'light');
+    };
+
+    apply((await window.__TAURI__.core.invoke('get_settings')).theme);
+    window.__TAURI__.event.listen('settings-changed', (event) => apply(event.payload.theme));
+};
+whenTauriReady(setupTheme);
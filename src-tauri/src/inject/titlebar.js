@@ -0,0 +1,325 @@
+// Labels for screen readers, in Messenger's language when we have it
+const titlebarStrings = {
+    en: {
+        titlebar: 'Window controls',
+        'messterial-settings': 'Messterial settings',
+        settings: 'Messenger settings',
+        minimize: 'Minimize',
+        maximize: 'Maximize',
+        restore: 'Restore',
+        close: 'Close',
+        accounts: 'Accounts',
+        popout: 'Open in new window',
+        downloads: 'Downloads',
+    },
+    pl: {
+        titlebar: 'Sterowanie oknem',
+        'messterial-settings': 'Ustawienia Messterial',
+        settings: 'Ustawienia Messengera',
+        minimize: 'Minimalizuj',
+        maximize: 'Maksymalizuj',
+        restore: 'Przywróć',
+        close: 'Zamknij',
+        accounts: 'Konta',
+        popout: 'Otwórz w nowym oknie',
+        downloads: 'Pobrane',
+    },
+};
+const titlebarLabel = (key) => {
+    const language = (document.documentElement.lang || navigator.language || 'en').slice(0, 2);
+    return (titlebarStrings[language] || titlebarStrings.en)[key];
+};
+
+const labelTitlebar = () => {
+    document.getElementById('custom-titlebar').setAttribute('aria-label', titlebarLabel('titlebar'));
+    document.querySelectorAll('#custom-titlebar .titlebar-button').forEach((button) => {
+        const label = titlebarLabel(button.dataset.label);
+        button.setAttribute('aria-label', label);
+        button.title = label;
+    });
+};
+
+// Puts the window buttons where the platform has them
+const layoutWindowControls = async () => {
+    const layout = await window.__TAURI__.core.invoke('window_controls_layout');
+    const titlebar = document.getElementById('custom-titlebar');
+
+    if (layout.native) {
+        titlebar.classList.add('native-controls');
+        return;
+    }
+    if (layout.snap_layouts) setupSnapLayouts();
+    setupResizeBorders();
+
+    const place = (container, buttons) => {
+        buttons.forEach((button) => container.appendChild(document.getElementById(`titlebar-${button}`)));
+    };
+    const left = titlebar.querySelector('.titlebar-controls-left');
+    const right = titlebar.querySelector('.titlebar-controls-right');
+    const shown = [...layout.left, ...layout.right];
+    ['minimize', 'maximize', 'close'].forEach((button) => {
+        document.getElementById(`titlebar-${button}`).hidden = !shown.includes(button);
+    });
+    place(left, layout.left);
+    place(right, layout.right);
+};
+
+// Windows 11 opens the snap layouts from a native hit area over the maximize
+// button, which takes the pointer from the page and reports it back instead
+const setupSnapLayouts = () => {
+    const invoke = window.__TAURI__.core.invoke;
+    const maximize = document.getElementById('titlebar-maximize');
+
+    const place = () => {
+        const rect = maximize.getBoundingClientRect();
+        const scale = window.devicePixelRatio;
+        invoke('place_maximize_button', {
+            rect: maximize.hidden || rect.width === 0 ? null : {
+                x: Math.round(rect.left * scale),
+                y: Math.round(rect.top * scale),
+                width: Math.round(rect.width * scale),
+                height: Math.round(rect.height * scale),
+            },
+        });
+    };
+    new ResizeObserver(place).observe(maximize);
+    window.addEventListener('resize', place);
+
+    window.__TAURI__.event.listen('maximize-button-state', (event) => {
+        maximize.classList.toggle('snap-hover', event.payload === 'hover');
+        maximize.classList.toggle('snap-pressed', event.payload === 'pressed');
+    });
+};
+
+// Invisible borders to resize the undecorated window from its edges and corners
+const setupResizeBorders = () => {
+    const appWindow = window.__TAURI__.window.getCurrentWindow();
+    const container = document.createElement('div');
+    container.id = 'messterial-resize-borders';
+
+    ['North', 'South', 'East', 'West', 'NorthEast', 'NorthWest', 'SouthEast', 'SouthWest'].forEach((direction) => {
+        const border = document.createElement('div');
+        border.className = `resize-border resize-${direction.toLowerCase()}`;
+        border.addEventListener('mousedown', (e) => {
+            if (e.button !== 0) return;
+            e.preventDefault();
+            appWindow.startResizeDragging(direction);
+        });
+        container.appendChild(border);
+    });
+    document.body.appendChild(container);
+
+    // Nothing to resize while maximized
+    const update = async () => {
+        container.hidden = await appWindow.isMaximized();
+    };
+    update();
+    appWindow.onResized(update);
+};
+
+const initWindowControls = () => {
+    if (!window.__TAURI__) return;
+    const appWindow = window.__TAURI__.window.getCurrentWindow();
+    labelTitlebar();
+    layoutWindowControls();
+
+    // Double-clicking the drag region maximizes through Tauri's data-tauri-drag-region,
+    // right-clicking it opens the window menu
+    document.querySelector('.titlebar-drag-region').addEventListener('contextmenu', (e) => {
+        if (!e.target.hasAttribute('data-tauri-drag-region')) return;
+        e.preventDefault();
+        window.__TAURI__.core.invoke('show_window_menu');
+    });
+
+    // The maximize button restores once the window is maximized
+    const maximize = document.getElementById('titlebar-maximize');
+    const updateMaximize = async () => {
+        maximize.dataset.label = (await appWindow.isMaximized()) ? 'restore' : 'maximize';
+        labelTitlebar();
+    };
+    updateMaximize();
+    appWindow.onResized(updateMaximize);
+
+    document.getElementById('titlebar-minimize').addEventListener('click', () => appWindow.minimize());
+    document.getElementById('titlebar-maximize').addEventListener('click', () => appWindow.toggleMaximize());
+    document.getElementById('titlebar-close').addEventListener('click', () => appWindow.close());
+    document.getElementById('titlebar-messterial-settings').addEventListener('click', () => window.__messterialOpenSettings());
+
+    document.getElementById('titlebar-settings').addEventListener('click', () => {
+        const selectors = [
+            'div[role="navigation"] div[role="button"][aria-label="Ustawienia użytkownika"]', // Polish
+            'div[role="navigation"] div[role="button"][aria-label="Account settings"]', // English
+            'div[role="navigation"] div[role="button"][aria-label*="Profil"]',
+            // Fallback: try to find the very last button in the hidden sidebar rail
+            'div[role="navigation"] > div:last-child div[role="button"]' 
+        ];
+        
+        for (const selector of selectors) {
+            const btn = document.querySelector(selector);
+            if (btn) {
+                console.log('Messterial: Found settings button via selector:', selector);
+                btn.click();
+                return;
+            }
+        }
+        console.error("Messterial: Could not find settings button.");
+    });
+};
+
+// Account switcher
+const setupAccountSwitcher = () => {
+    const container = document.getElementById('titlebar-accounts');
+    const current = document.getElementById('titlebar-account-current');
+    const menu = document.getElementById('titlebar-account-menu');
+    const invoke = window.__TAURI__.core.invoke;
+
+    const isTabs = window.__MESSTERIAL_LAYOUT__ === 'tabs';
+    current.textContent = isTabs ? 'Accounts' : window.__MESSTERIAL_ACCOUNT__.name;
+
+    const renderMenu = async () => {
+        const accounts = await invoke('list_accounts');
+        menu.innerHTML = '';
+
+        accounts.forEach((account) => {
+            const item = document.createElement('div');
+            item.className = 'account-item';
+            item.setAttribute('role', 'menuitem');
+            item.tabIndex = -1;
+            if (account.id === window.__MESSTERIAL_ACCOUNT__.id) item.classList.add('active');
+
+            const name = document.createElement('span');
+            name.textContent = account.name;
+            item.appendChild(name);
+
+            if (account.id !== 'default') {
+                const remove = document.createElement('button');
+                remove.type = 'button';
+                remove.className = 'account-remove';
+                remove.textContent = '✕';
+                remove.title = `Remove ${account.name}`;
+                remove.setAttribute('aria-label', remove.title);
+                remove.addEventListener('click', async (e) => {
+                    e.stopPropagation();
+                    // Confirmed natively, the page can't answer for the user
+                    if (await invoke('remove_account', { id: account.id })) renderMenu();
+                });
+                item.appendChild(remove);
+            }
+
+            item.addEventListener('click', () => {
+                container.classList.remove('open');
+                invoke('switch_account', { id: account.id });
+            });
+            menu.appendChild(item);
+        });
+
+        const add = document.createElement('div');
+        add.className = 'account-item';
+        add.setAttribute('role', 'menuitem');
+        add.tabIndex = -1;
+        add.textContent = '+ Add account';
+        add.addEventListener('click', async () => {
+            container.classList.remove('open');
+            const name = prompt('Account name');
+            if (!name) return;
+            const account = await invoke('add_account', { name });
+            invoke('switch_account', { id: account.id });
+        });
+        menu.appendChild(add);
+
+        const layout = document.createElement('div');
+        layout.className = 'account-item';
+        layout.setAttribute('role', 'menuitem');
+        layout.tabIndex = -1;
+        layout.textContent = isTabs ? 'Use separate windows' : 'Use tabs in one window';
+        layout.addEventListener('click', () => {
+            container.classList.remove('open');
+            if (!confirm('Messterial will restart to change the layout.')) return;
+            invoke('set_account_layout', { layout: isTabs ? 'windows' : 'tabs' });
+        });
+        menu.appendChild(layout);
+    };
+
+    current.setAttribute('aria-label', `${titlebarLabel('accounts')}: ${current.textContent}`);
+
+    const setOpen = (open) => {
+        container.classList.toggle('open', open);
+        current.setAttribute('aria-expanded', open);
+    };
+
+    current.addEventListener('click', async () => {
+        const open = !container.classList.contains('open');
+        if (open) await renderMenu();
+        setOpen(open);
+        if (open) menu.querySelector('[role="menuitem"]')?.focus();
+    });
+
+    // Arrow keys move between the items, Enter/Space picks one, Escape closes
+    menu.addEventListener('keydown', (e) => {
+        const items = Array.from(menu.querySelectorAll('[role="menuitem"]'));
+        const index = items.indexOf(document.activeElement);
+        if (e.key === 'ArrowDown') items[(index + 1) % items.length]?.focus();
+        else if (e.key === 'ArrowUp') items[(index - 1 + items.length) % items.length]?.focus();
+        else if (e.key === 'Enter' || e.key === ' ') document.activeElement.click();
+        else if (e.key === 'Escape') {
+            setOpen(false);
+            current.focus();
+        } else return;
+        e.preventDefault();
+    });
+
+    document.addEventListener('click', (e) => {
+        if (!container.contains(e.target)) setOpen(false);
+    });
+
+    if (isTabs) setupAccountTabs();
+};
+
+// Tab strip for the tabbed multi-account layout
+const setupAccountTabs = async () => {
+    const strip = document.getElementById('titlebar-tabs');
+    const invoke = window.__TAURI__.core.invoke;
+    let accounts = await invoke('list_accounts');
+    let counts = await invoke('unread_counts');
+
+    const renderTabs = () => {
+        strip.innerHTML = '';
+        accounts.forEach((account) => {
+            const tab = document.createElement('button');
+            tab.type = 'button';
+            tab.className = 'account-tab';
+            tab.setAttribute('role', 'tab');
+            const selected = account.id === window.__MESSTERIAL_ACCOUNT__.id;
+            tab.setAttribute('aria-selected', selected);
+            if (selected) tab.classList.add('active');
+            tab.textContent = account.name;
+
+            const unread = counts[account.id] || 0;
+            if (unread > 0) {
+                const badge = document.createElement('span');
+                badge.className = 'account-tab-badge';
+                badge.textContent = unread > 9 ? '9+' : unread.toString();
+                tab.setAttribute('aria-label', `${account.name}, ${unread}`);
+                tab.appendChild(badge);
+            }
+
+            tab.addEventListener('click', () => invoke('switch_account', { id: account.id }));
+            strip.appendChild(tab);
+        });
+    };
+
+    renderTabs();
+    window.__TAURI__.event.listen('accounts-unread', (event) => {
+        counts = event.payload;
+        renderTabs();
+    });
+    window.__TAURI__.event.listen('accounts-changed', (event) => {
+        accounts = event.payload;
+        renderTabs();
+    });
+};
+
+whenTauriReady(() => {
+    initWindowControls();
+    setupAccountSwitcher();
+});
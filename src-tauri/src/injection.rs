@@ -0,0 +1,664 @@
+use std::borrow::Cow;
+#[cfg(debug_assertions)]
+use tauri::Manager;
+use tauri::{AppHandle, Runtime};
+
+use crate::accounts::{self, Account, AccountLayout};
+use crate::settings::{
+    self, Animation, Density, Feature, ScrollbarColor, Scrollbars, Settings, Theme,
+};
+use crate::user_content::{self, RunAt, UserContent};
+#[cfg(debug_assertions)]
+use crate::watcher;
+use crate::zoom;
+
+/// Folder the injected sources are bundled from. Debug builds read them from
+/// here at runtime, so edits show up without rebuilding.
+#[cfg(debug_assertions)]
+const SOURCE_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/inject");
+
+/// One piece of the injection bundle. Sources live in `src/inject/`.
+struct Module {
+    /// File name of the sources, without the extension.
+    name: &'static str,
+    /// Settings toggle, `None` for the pieces that are always injected.
+    feature: Option<Feature>,
+    css: &'static str,
+    js: &'static str,
+}
+
+impl Module {
+    fn css(&self) -> Cow<'static, str> {
+        source(&format!("{}.css", self.name), self.css)
+    }
+
+    fn js(&self) -> Cow<'static, str> {
+        source(&format!("{}.js", self.name), self.js)
+    }
+}
+
+/// The source of `file`, fresh from disk in debug builds.
+#[cfg_attr(not(debug_assertions), allow(unused_variables))]
+fn source(file: &str, bundled: &'static str) -> Cow<'static, str> {
+    #[cfg(debug_assertions)]
+    if !bundled.is_empty() {
+        if let Ok(source) = std::fs::read_to_string(std::path::Path::new(SOURCE_DIR).join(file)) {
+            return Cow::Owned(source);
+        }
+    }
+
+    Cow::Borrowed(bundled)
+}
+
+// =========================================================================
+//                               MODULES
+// =========================================================================
+const MODULES: &[Module] = &[
+    Module {
+        name: "base",
+        feature: None,
+        css: include_str!("inject/base.css"),
+        js: include_str!("inject/base.js"),
+    },
+    Module {
+        name: "theme",
+        feature: None,
+        css: "",
+        js: include_str!("inject/theme.js"),
+    },
+    Module {
+        name: "layout",
+        feature: Some(Feature::Layout),
+        css: include_str!("inject/layout.css"),
+        js: "",
+    },
+    Module {
+        name: "bloat",
+        feature: Some(Feature::Bloat),
+        css: include_str!("inject/bloat.css"),
+        js: "",
+    },
+    Module {
+        name: "titlebar",
+        feature: Some(Feature::Titlebar),
+        css: include_str!("inject/titlebar.css"),
+        js: include_str!("inject/titlebar.js"),
+    },
+    Module {
+        name: "scrollbars",
+        feature: Some(Feature::Scrollbars),
+        css: include_str!("inject/scrollbars.css"),
+        js: "",
+    },
+    Module {
+        name: "bubbles",
+        feature: Some(Feature::Bubbles),
+        css: include_str!("inject/bubbles.css"),
+        js: "",
+    },
+    Module {
+        name: "animations",
+        feature: None,
+        css: include_str!("inject/animations.css"),
+        js: "",
+    },
+    Module {
+        name: "analytics",
+        feature: Some(Feature::Analytics),
+        css: "",
+        js: include_str!("inject/analytics.js"),
+    },
+    Module {
+        name: "links",
+        feature: Some(Feature::Links),
+        css: "",
+        js: include_str!("inject/links.js"),
+    },
+    Module {
+        name: "badges",
+        feature: Some(Feature::Badges),
+        css: "",
+        js: include_str!("inject/badges.js"),
+    },
+    Module {
+        name: "shortcuts",
+        feature: Some(Feature::Shortcuts),
+        css: "",
+        js: include_str!("inject/shortcuts.js"),
+    },
+    Module {
+        name: "switcher",
+        feature: Some(Feature::Switcher),
+        css: include_str!("inject/switcher.css"),
+        js: include_str!("inject/switcher.js"),
+    },
+    Module {
+        name: "downloads",
+        feature: Some(Feature::Downloads),
+        css: include_str!("inject/downloads.css"),
+        js: include_str!("inject/downloads.js"),
+    },
+    Module {
+        name: "media",
+        feature: Some(Feature::Media),
+        css: "",
+        js: include_str!("inject/media.js"),
+    },
+    Module {
+        name: "popout",
+        feature: Some(Feature::Popout),
+        css: include_str!("inject/popout.css"),
+        js: include_str!("inject/popout.js"),
+    },
+    Module {
+        name: "settings",
+        feature: None,
+        css: include_str!("inject/settings.css"),
+        js: include_str!("inject/settings.js"),
+    },
+];
+
+const TITLEBAR_HTML: &str = include_str!("inject/titlebar.html");
+const LIGHT_CSS: &str = include_str!("inject/light.css");
+const HIGH_CONTRAST_CSS: &str = include_str!("inject/high-contrast.css");
+
+/// Added to the initialization script of conversations popped out into their
+/// own window, for the minimal layout in `popout.css`.
+pub const POPOUT_SCRIPT: &str = "
+    window.__MESSTERIAL_POPOUT__ = true;
+    document.documentElement.classList.add('messterial-popout');
+";
+
+/// Modules that call windows need as well, next to the script of the base
+/// module. Its stylesheet lays out the chat pages, so it's left out.
+const CALL_MODULES: [&str; 1] = ["theme"];
+const CALL_HTML: &str = include_str!("inject/call.html");
+const CALL_CSS: &str = include_str!("inject/call.css");
+const CALL_JS: &str = include_str!("inject/call.js");
+const SCREEN_SHARE_JS: &str = include_str!("inject/screenshare.js");
+
+fn enabled_modules(settings: &Settings) -> impl Iterator<Item = &'static Module> + '_ {
+    MODULES.iter().filter(|module| {
+        module
+            .feature
+            .is_none_or(|feature| settings.features.is_enabled(feature))
+    })
+}
+
+/// Whether going from `previous` to `next` adds or removes scripts, which
+/// only takes effect once the pages are loaded again.
+pub fn scripts_changed(previous: &Settings, next: &Settings) -> bool {
+    MODULES.iter().any(|module| {
+        !module.js.is_empty()
+            && module.feature.is_some_and(|feature| {
+                previous.features.is_enabled(feature) != next.features.is_enabled(feature)
+            })
+    })
+}
+
+/// Scales every `font-size: <n>px` declaration in `css` by `scale`.
+fn scale_font_sizes(css: &str, scale: f64) -> String {
+    const PROPERTY: &str = "font-size:";
+
+    let mut scaled = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(index) = rest.find(PROPERTY) {
+        let (before, after) = rest.split_at(index + PROPERTY.len());
+        scaled.push_str(before);
+
+        let value = after.trim_start();
+        scaled.push_str(&after[..after.len() - value.len()]);
+
+        let end = value
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(value.len());
+        match value[..end].parse::<f64>() {
+            Ok(size) if value[end..].starts_with("px") => {
+                scaled.push_str(&format!("{}px", (size * scale * 10.0).round() / 10.0));
+                rest = &value[end + 2..];
+            }
+            _ => rest = value,
+        }
+    }
+    scaled.push_str(rest);
+
+    scaled
+}
+
+/// Overrides the motion variables of `base.css`, which are the full preset.
+fn motion_css(animation: Animation) -> &'static str {
+    match animation {
+        Animation::Full => "",
+        Animation::System => {
+            "@media (prefers-reduced-motion: reduce) { :root { --motion-zoom: 0; } }\n"
+        }
+        Animation::Reduced => ":root { --motion-zoom: 0; }\n",
+        Animation::None => ":root { --motion-speed: 0; --motion-zoom: 0; }\n",
+    }
+}
+
+/// Overrides the spacing variables of `base.css`, which are the comfortable preset.
+fn density_css(density: Density) -> String {
+    let (gap, middle_gap, card_radius, row_radius, row_margin, row_padding, avatar) = match density
+    {
+        Density::Comfortable => return String::new(),
+        Density::Compact => (4, 0, 12, 16, "1px 4px", 4, 40),
+        Density::Spacious => (12, 4, 24, 28, "4px 6px", 12, 56),
+    };
+
+    format!(
+        ":root {{
+    --gap-size: {gap}px;
+    --middle-gap: {middle_gap}px;
+    --card-radius: {card_radius}px;
+    --row-radius: {row_radius}px;
+    --row-margin: {row_margin};
+}}
+div[role=\"navigation\"] div[role=\"row\"] a {{
+    padding-top: {row_padding}px !important;
+    padding-bottom: {row_padding}px !important;
+}}
+div[role=\"navigation\"] div[role=\"row\"] a svg[role=\"img\"]:is([width=\"48\"], [width=\"56\"]) {{
+    width: {avatar}px !important;
+    height: {avatar}px !important;
+}}
+"
+    )
+}
+
+/// Variables and extra rules for `scrollbars.css`.
+fn scrollbar_css(scrollbars: &Scrollbars) -> String {
+    let (color, thumb, hover) = match scrollbars.color {
+        ScrollbarColor::Neutral => ("var(--md-sys-color-on-surface)", 15, 30),
+        ScrollbarColor::Accent => ("var(--md-sys-color-primary)", 45, 70),
+    };
+
+    let mut css = format!(
+        ":root {{
+    --scrollbar-width: {width}px;
+    --scrollbar-thumb: color-mix(in srgb, {color} {thumb}%, transparent);
+    --scrollbar-thumb-hover: color-mix(in srgb, {color} {hover}%, transparent);
+}}
+",
+        width = scrollbars.width,
+        color = color,
+        thumb = thumb,
+        hover = hover
+    );
+
+    if scrollbars.auto_hide {
+        css.push_str(
+            "*:not(:hover)::-webkit-scrollbar-thumb { background-color: transparent !important; }\n",
+        );
+    }
+
+    css
+}
+
+/// Overrides of the dark palette for the other themes.
+fn theme_css(theme: Theme) -> Cow<'static, str> {
+    match theme {
+        Theme::Dark => Cow::Borrowed(""),
+        Theme::Light => source("light.css", LIGHT_CSS),
+        Theme::HighContrast => source("high-contrast.css", HIGH_CONTRAST_CSS),
+    }
+}
+
+/// Stylesheet made of the enabled modules, with the font sizes multiplied by
+/// `font_scale`.
+pub fn stylesheet(settings: &Settings, font_scale: f64) -> String {
+    // Styled scrollbars can't overlay the content, the native ones can
+    let native_scrollbars = settings.scrollbars.overlay;
+
+    let mut css: String = enabled_modules(settings)
+        .filter(|module| !(native_scrollbars && module.feature == Some(Feature::Scrollbars)))
+        .map(|module| module.css())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    css.push('\n');
+    css.push_str(motion_css(settings.animation));
+
+    if settings.features.layout {
+        css.push('\n');
+        css.push_str(&density_css(settings.density));
+    }
+
+    if settings.features.scrollbars && !native_scrollbars {
+        css.push('\n');
+        css.push_str(&scrollbar_css(&settings.scrollbars));
+    }
+
+    css.push('\n');
+    css.push_str(&theme_css(settings.theme));
+
+    if !settings.features.titlebar {
+        css.push_str("\n:root { --titlebar-height: 0px; }\n");
+    }
+
+    if (font_scale - 1.0).abs() > f64::EPSILON {
+        css = scale_font_sizes(&css, font_scale);
+        // Messenger sizes its text in rem
+        css.push_str(&format!(
+            "\nhtml {{ font-size: {}px !important; }}\n",
+            16.0 * font_scale
+        ));
+    }
+
+    css
+}
+
+fn js_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "\"\"".into())
+}
+
+// =========================================================================
+//                            LAYOUT MANAGER
+// =========================================================================
+
+/// Builds the initialization script injected into a Messenger webview that
+/// belongs to `account`, shown using `layout`. Only the modules enabled in
+/// `settings` are included, followed by the user's own `user` content.
+pub fn init_script(
+    account: &Account,
+    layout: AccountLayout,
+    settings: &Settings,
+    user: &UserContent,
+    font_scale: f64,
+) -> String {
+    let titlebar = if settings.features.titlebar {
+        format!(
+            "document.body.insertAdjacentHTML('afterbegin', {});",
+            js_string(&source("titlebar.html", TITLEBAR_HTML))
+        )
+    } else {
+        String::new()
+    };
+
+    let scripts: String = enabled_modules(settings)
+        .map(|module| module.js())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        "
+        window.__MESSTERIAL_ACCOUNT__ = {account};
+        window.__MESSTERIAL_LAYOUT__ = {layout};
+
+        {matcher}
+        {user_start}
+
+        window.addEventListener('DOMContentLoaded', () => {{
+            const style = document.createElement('style');
+            style.id = 'messterial-style';
+            style.textContent = {css};
+            document.head.append(style);
+
+            const userStyle = document.createElement('style');
+            userStyle.id = 'messterial-user-style';
+            userStyle.textContent = {user_css};
+            document.head.append(userStyle);
+
+            {titlebar}
+
+            {scripts}
+
+            {user_end}
+        }});
+        ",
+        account = serde_json::to_string(account).unwrap_or_else(|_| "null".into()),
+        layout = serde_json::to_string(&layout).unwrap_or_else(|_| "null".into()),
+        css = js_string(&stylesheet(settings, font_scale)),
+        titlebar = titlebar,
+        scripts = scripts,
+        matcher = user_content::MATCHER_JS,
+        user_start = user.init_scripts(RunAt::Start),
+        user_css = js_string(&user.css),
+        user_end = user.init_scripts(RunAt::End)
+    )
+}
+
+/// Builds the initialization script of a call window: the screen share
+/// picker, and with the titlebar feature on, its own titlebar in the current
+/// theme, without the modules made for the chat pages.
+pub fn call_script(settings: &Settings) -> String {
+    let titlebar = if settings.features.titlebar {
+        let modules: Vec<&Module> = MODULES
+            .iter()
+            .filter(|module| CALL_MODULES.contains(&module.name))
+            .collect();
+
+        format!(
+            "
+            const style = document.createElement('style');
+            style.id = 'messterial-style';
+            style.textContent = {css};
+            document.head.append(style);
+
+            document.body.insertAdjacentHTML('afterbegin', {html});
+
+            {scripts}
+            {call}
+            ",
+            css = js_string(&format!(
+                "{}\n{}\n{}\n{}",
+                source("call.css", CALL_CSS),
+                modules
+                    .iter()
+                    .map(|module| module.css())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                motion_css(settings.animation),
+                theme_css(settings.theme)
+            )),
+            html = js_string(&source("call.html", CALL_HTML)),
+            scripts = modules
+                .iter()
+                .map(|module| module.js())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            call = source("call.js", CALL_JS)
+        )
+    } else {
+        String::new()
+    };
+
+    format!(
+        "
+        window.addEventListener('DOMContentLoaded', () => {{
+            // Calls start out on about:blank
+            if (location.protocol !== 'https:') return;
+
+            {base}
+            {titlebar}
+            {screen_share}
+        }});
+        ",
+        base = MODULES[0].js(),
+        titlebar = titlebar,
+        screen_share = source("screenshare.js", SCREEN_SHARE_JS)
+    )
+}
+
+/// Replaces the injected stylesheet of every open Messenger webview with one
+/// built from the current settings and the font scale of its window.
+pub fn refresh_styles<R: Runtime>(app: &AppHandle<R>) {
+    let settings = settings::current(app);
+
+    for webview in accounts::account_webviews(app) {
+        let font_scale = zoom::current(app, webview.window().label()).font_scale;
+        let script = format!(
+            "(() => {{
+                const previous = document.getElementById('messterial-style');
+                if (!previous) return;
+                const style = document.createElement('style');
+                style.id = 'messterial-style';
+                style.textContent = {};
+                previous.replaceWith(style);
+            }})();",
+            js_string(&stylesheet(&settings, font_scale))
+        );
+        let _ = webview.eval(&script);
+    }
+}
+
+// =========================================================================
+//                              HOT RELOAD
+// =========================================================================
+
+/// Keeps the source watcher of debug builds alive.
+#[cfg(debug_assertions)]
+struct SourceWatcher {
+    _watcher: std::sync::Mutex<notify::RecommendedWatcher>,
+}
+
+/// Evaluates a changed script module again in every Messenger webview, next to
+/// `whenTauriReady` from the base module. Listeners added by the previous
+/// version stay around until the app restarts.
+#[cfg(debug_assertions)]
+fn reload_script<R: Runtime>(app: &AppHandle<R>, module: &Module) {
+    let settings = settings::current(app);
+    if !enabled_modules(&settings).any(|enabled| enabled.name == module.name) {
+        return;
+    }
+
+    let base = MODULES[0].js();
+    let script = if module.name == MODULES[0].name {
+        format!("(() => {{\n{}\n}})();", base)
+    } else {
+        format!("(() => {{\n{}\n{}\n}})();", base, module.js())
+    };
+
+    for webview in accounts::account_webviews(app) {
+        let _ = webview.eval(&script);
+    }
+}
+
+/// Watches `src/inject/` in debug builds and applies edits to the live
+/// webviews without reloading them, so the Messenger session stays as it is.
+#[cfg(debug_assertions)]
+pub fn watch_sources<R: Runtime>(app: &AppHandle<R>) {
+    let app_handle = app.clone();
+    let result = watcher::watch(&[SOURCE_DIR.into()], move |paths| {
+        let mut styles_changed = false;
+
+        for path in &paths {
+            let (Some(name), Some(ext)) = (path.file_stem(), path.extension()) else {
+                continue;
+            };
+
+            if ext == "css" {
+                styles_changed = true;
+            } else if ext == "js" {
+                let module = MODULES
+                    .iter()
+                    .find(|module| *name == *module.name && !module.js.is_empty());
+                if let Some(module) = module {
+                    log::info!("Reloading {}.js", module.name);
+                    reload_script(&app_handle, module);
+                }
+            }
+        }
+
+        if styles_changed {
+            log::info!("Reloading styles");
+            refresh_styles(&app_handle);
+        }
+    });
+
+    match result {
+        Ok(watcher) => {
+            app.manage(SourceWatcher {
+                _watcher: std::sync::Mutex::new(watcher),
+            });
+        }
+        Err(e) => log::warn!("Failed to watch {}: {}", SOURCE_DIR, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scale_font_sizes_scales_pixel_sizes() {
+        assert_eq!(
+            scale_font_sizes("a { font-size: 10px; }", 1.5),
+            "a { font-size: 15px; }"
+        );
+        assert_eq!(
+            scale_font_sizes("a{font-size:12px!important}", 2.0),
+            "a{font-size:24px!important}"
+        );
+    }
+
+    #[test]
+    fn scale_font_sizes_rounds_to_a_tenth() {
+        assert_eq!(
+            scale_font_sizes("font-size: 13px;", 1.15),
+            "font-size: 15px;"
+        );
+        assert_eq!(
+            scale_font_sizes("font-size: 12.5px;", 0.85),
+            "font-size: 10.6px;"
+        );
+    }
+
+    #[test]
+    fn scale_font_sizes_keeps_other_units_and_properties() {
+        let css = "a { font-size: 1.2em; line-height: 20px; }\
+                   b { font-size: var(--size); } c { font-size: 80%; }";
+        assert_eq!(scale_font_sizes(css, 2.0), css);
+    }
+
+    #[test]
+    fn scale_font_sizes_scales_every_declaration() {
+        assert_eq!(
+            scale_font_sizes(
+                "a { font-size: 10px } b { font-size: 1em } c { --title-font-size: 20px }",
+                0.5
+            ),
+            "a { font-size: 5px } b { font-size: 1em } c { --title-font-size: 10px }"
+        );
+    }
+
+    #[test]
+    fn scale_font_sizes_handles_a_cut_off_declaration() {
+        assert_eq!(scale_font_sizes("a { font-size:", 2.0), "a { font-size:");
+        assert_eq!(scale_font_sizes("font-size: 4", 2.0), "font-size: 4");
+    }
+
+    #[test]
+    fn density_css_overrides_the_spacing_of_other_presets() {
+        assert_eq!(density_css(Density::Comfortable), "");
+
+        let compact = density_css(Density::Compact);
+        assert!(compact.contains("--gap-size: 4px;"));
+        assert!(compact.contains("--row-margin: 1px 4px;"));
+        assert!(compact.contains("padding-top: 4px !important;"));
+        assert!(compact.contains("width: 40px !important;"));
+
+        let spacious = density_css(Density::Spacious);
+        assert!(spacious.contains("--gap-size: 12px;"));
+        assert!(spacious.contains("--row-margin: 4px 6px;"));
+        assert!(spacious.contains("padding-top: 12px !important;"));
+        assert!(spacious.contains("width: 56px !important;"));
+    }
+
+    #[test]
+    fn motion_css_turns_down_the_motion_variables() {
+        assert_eq!(motion_css(Animation::Full), "");
+        assert_eq!(
+            motion_css(Animation::System),
+            "@media (prefers-reduced-motion: reduce) { :root { --motion-zoom: 0; } }\n"
+        );
+        assert_eq!(
+            motion_css(Animation::Reduced),
+            ":root { --motion-zoom: 0; }\n"
+        );
+        assert_eq!(
+            motion_css(Animation::None),
+            ":root { --motion-speed: 0; --motion-zoom: 0; }\n"
+        );
+    }
+}
@@ -0,0 +1,40 @@
+use clap::Parser;
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::accounts::{self, AccountManager};
+use crate::cli::Options;
+use crate::deep_link;
+
+/// Runs in the already running instance when Messterial is launched again.
+/// Brings the active account's window forward and acts on the new launch's
+/// arguments, so shortcuts and links reuse the existing session instead of
+/// starting a second one on the same storage.
+pub fn on_second_instance<R: Runtime>(app: &AppHandle<R>, args: Vec<String>, cwd: String) {
+    log::info!("Second instance launched from {} with {:?}", cwd, args);
+
+    let options = match Options::try_parse_from(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            log::warn!("Ignoring arguments of second instance: {}", e);
+            accounts::focus_active(app);
+            return;
+        }
+    };
+
+    let manager = app.state::<AccountManager>();
+    let account = options
+        .profile
+        .as_deref()
+        .and_then(|profile| accounts::find_account(&manager, profile));
+
+    match account {
+        Some(account) => {
+            if let Err(e) = accounts::show_account(app, &account) {
+                log::error!("Failed to show account {}: {}", account.id, e);
+            }
+        }
+        None => accounts::focus_active(app),
+    }
+
+    deep_link::handle_links(app, &options.thread_links());
+}
@@ -1,6 +1,8 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use clap::Parser;
+
 fn main() {
-    messterial_lib::run()
+    messterial_lib::run(messterial_lib::Options::parse())
 }
@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::image::Image;
+use tauri::{
+    AppHandle, Emitter, Manager, Runtime, UriSchemeContext, Url, Webview, WebviewUrl,
+    WebviewWindowBuilder,
+};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::{cli, downloads, url_policy};
+
+/// URI scheme the viewer page is served from. Pages of the app's own schemes
+/// count as local, so the viewer can call commands without a capability for
+/// Facebook's CDN.
+pub const SCHEME: &str = "messterial-media";
+
+const VIEWER_HTML: &str = include_str!("viewer/index.html");
+const VIEWER_CSS: &str = include_str!("viewer/viewer.css");
+const VIEWER_JS: &str = include_str!("viewer/viewer.js");
+
+/// Label of the viewer window, not the one of Messenger's own media popups.
+const LABEL: &str = "media";
+
+/// Profile of the viewer window. It only loads public CDN URLs, so it doesn't
+/// need an account's cookies, and WebView2 can't share a profile between
+/// webviews created with different arguments anyway.
+const PROFILE_DIR: &str = "media-viewer";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaKind {
+    Image,
+    Video,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaItem {
+    pub url: String,
+    pub kind: MediaKind,
+}
+
+/// Media of a thread shown in the viewer, and the one to start with.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Gallery {
+    pub items: Vec<MediaItem>,
+    pub index: usize,
+}
+
+/// What the viewer window shows.
+#[derive(Default)]
+pub struct MediaViewer {
+    gallery: Mutex<Gallery>,
+}
+
+/// Serves the viewer page.
+pub fn protocol<R: Runtime>(
+    _context: UriSchemeContext<'_, R>,
+    request: Request<Vec<u8>>,
+) -> Response<Cow<'static, [u8]>> {
+    let (content_type, body) = match request.uri().path() {
+        "/" | "/index.html" => ("text/html", VIEWER_HTML),
+        "/viewer.css" => ("text/css", VIEWER_CSS),
+        "/viewer.js" => ("text/javascript", VIEWER_JS),
+        _ => {
+            return Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Cow::Borrowed(&[][..]))
+                .unwrap_or_default();
+        }
+    };
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .body(Cow::Borrowed(body.as_bytes()))
+        .unwrap_or_default()
+}
+
+/// Guesses whether a CDN URL opened by Messenger is a photo or a video.
+pub fn media_item(url: &Url) -> MediaItem {
+    let kind = if url.path().ends_with(".mp4") {
+        MediaKind::Video
+    } else {
+        MediaKind::Image
+    };
+    MediaItem {
+        url: url.to_string(),
+        kind,
+    }
+}
+
+/// Shows `gallery` in the viewer window, opening it if needed. Anything but
+/// media on Facebook's CDN is left out.
+pub fn show<R: Runtime>(app: &AppHandle<R>, mut gallery: Gallery) -> tauri::Result<()> {
+    let Some(state) = app.try_state::<MediaViewer>() else {
+        return Ok(());
+    };
+
+    let current = gallery
+        .items
+        .get(gallery.index)
+        .map(|item| item.url.clone());
+    gallery
+        .items
+        .retain(|item| Url::parse(&item.url).is_ok_and(|url| url_policy::is_media_file(&url)));
+    if gallery.items.is_empty() {
+        return Ok(());
+    }
+    gallery.index = current
+        .and_then(|url| gallery.items.iter().position(|item| item.url == url))
+        .unwrap_or_default();
+
+    *state.gallery.lock().unwrap() = gallery.clone();
+
+    if let Some(window) = app.get_webview_window(LABEL) {
+        let _ = app.emit_to(LABEL, "media-viewer-gallery", gallery);
+        let _ = window.unminimize();
+        let _ = window.show();
+        return window.set_focus();
+    }
+
+    let url = url_policy::app_page_url(SCHEME);
+    WebviewWindowBuilder::new(app, LABEL, WebviewUrl::CustomProtocol(url))
+        .title("Media")
+        .inner_size(960.0, 720.0)
+        .min_inner_size(400.0, 300.0)
+        .center()
+        .devtools(cli::devtools_enabled(app))
+        .data_directory(app.path().app_local_data_dir()?.join(PROFILE_DIR))
+        .build()?;
+    Ok(())
+}
+
+/// Media at `url` if it may be loaded by the viewer.
+fn media_url(url: &str) -> Result<Url, String> {
+    Url::parse(url)
+        .ok()
+        .filter(url_policy::is_media_file)
+        .ok_or_else(|| "Not a Messenger photo or video".into())
+}
+
+// =========================================================================
+//                               COMMANDS
+// =========================================================================
+
+/// Opens the viewer with the media of a thread, starting at `index`.
+#[tauri::command]
+pub async fn open_media_viewer<R: Runtime>(
+    app: AppHandle<R>,
+    items: Vec<MediaItem>,
+    index: usize,
+) -> Result<(), String> {
+    show(&app, Gallery { items, index }).map_err(|e| e.to_string())
+}
+
+/// What the viewer shows, asked for by the page once it's loaded.
+#[tauri::command]
+pub fn media_viewer_gallery(state: tauri::State<'_, MediaViewer>) -> Gallery {
+    state.gallery.lock().unwrap().clone()
+}
+
+/// Saves the media at `url` like a download. Returns where it went, or `None`
+/// when the user cancelled.
+#[tauri::command]
+pub async fn save_media<R: Runtime>(
+    app: AppHandle<R>,
+    webview: Webview<R>,
+    url: String,
+) -> Result<Option<PathBuf>, String> {
+    let url = media_url(&url)?;
+    let label = webview.window().label().to_string();
+    downloads::save_url(&app, url, &label).await
+}
+
+/// Copies the photo at `url` to the clipboard.
+#[tauri::command]
+pub async fn copy_media<R: Runtime>(app: AppHandle<R>, url: String) -> Result<(), String> {
+    let url = media_url(&url)?;
+    let bytes = downloads::fetch(&url).await?;
+
+    let image = image::load_from_memory(&bytes)
+        .map_err(|e| e.to_string())?
+        .to_rgba8();
+    let (width, height) = image.dimensions();
+
+    app.clipboard()
+        .write_image(&Image::new_owned(image.into_raw(), width, height))
+        .map_err(|e| e.to_string())
+}
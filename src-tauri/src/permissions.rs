@@ -0,0 +1,422 @@
+// The broker has nothing to hook into on macOS
+#![cfg_attr(
+    not(any(target_os = "linux", windows)),
+    allow(dead_code, unused_imports)
+)]
+
+use rfd::{AsyncMessageDialog, MessageButtons, MessageDialogResult, MessageLevel};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use tauri::{AppHandle, Manager, Runtime, Url, Webview, Window};
+
+use crate::settings::{self, MediaPermission, MediaPermissions, PermissionDecision, SettingsStore};
+use crate::url_policy;
+
+const ALLOW: &str = "Allow";
+const BLOCK: &str = "Block";
+const NOT_NOW: &str = "Not now";
+
+/// A permission request of the webview waiting for the user's answer. Only
+/// lives on the main thread, like the webview objects it holds.
+#[cfg(target_os = "linux")]
+type PlatformRequest = webkit2gtk::PermissionRequest;
+
+#[cfg(windows)]
+struct PlatformRequest {
+    args: webview2_com::Microsoft::Web::WebView2::Win32::ICoreWebView2PermissionRequestedEventArgs,
+    deferral: webview2_com::Microsoft::Web::WebView2::Win32::ICoreWebView2Deferral,
+}
+
+#[cfg(any(target_os = "linux", windows))]
+thread_local! {
+    static PLATFORM_REQUESTS: RefCell<HashMap<u32, PlatformRequest>> = RefCell::new(HashMap::new());
+}
+
+/// Requests waiting for the user's answer, by id in `PLATFORM_REQUESTS`.
+#[derive(Default)]
+pub struct PermissionBroker {
+    next_id: AtomicU32,
+}
+
+/// Answer to a request from the `remembered` decisions: `None` when the user
+/// needs to be asked. Anything but Messenger is denied.
+fn decide(
+    origin: &str,
+    permissions: &[MediaPermission],
+    remembered: &MediaPermissions,
+) -> Option<bool> {
+    let is_messenger = Url::parse(origin).is_ok_and(|url| url_policy::is_messenger(&url));
+    if !is_messenger || permissions.is_empty() {
+        return Some(false);
+    }
+
+    let decisions: Vec<PermissionDecision> = permissions
+        .iter()
+        .map(|permission| remembered.get(*permission))
+        .collect();
+
+    if decisions.contains(&PermissionDecision::Deny) {
+        Some(false)
+    } else if decisions
+        .iter()
+        .all(|decision| *decision == PermissionDecision::Allow)
+    {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+/// "camera", "camera and microphone", ...
+fn describe(permissions: &[MediaPermission]) -> String {
+    let names: Vec<&str> = permissions
+        .iter()
+        .map(|permission| match permission {
+            MediaPermission::Camera => "camera",
+            MediaPermission::Microphone => "microphone",
+            MediaPermission::Screen => "screen",
+        })
+        .collect();
+    match names.split_last() {
+        Some((last, [])) => last.to_string(),
+        Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+        None => String::new(),
+    }
+}
+
+/// Asks the user with a native dialog over `window`, which the page can't
+/// answer by itself. Returns whether to allow, and the decision to remember.
+async fn ask<R: Runtime>(
+    window: Option<Window<R>>,
+    permissions: &[MediaPermission],
+) -> (bool, Option<PermissionDecision>) {
+    let mut dialog = AsyncMessageDialog::new()
+        .set_level(MessageLevel::Info)
+        .set_title("Messterial")
+        .set_description(format!(
+            "Messenger wants to use your {}.",
+            describe(permissions)
+        ))
+        .set_buttons(MessageButtons::YesNoCancelCustom(
+            ALLOW.into(),
+            BLOCK.into(),
+            NOT_NOW.into(),
+        ));
+    if let Some(window) = &window {
+        dialog = dialog.set_parent(window);
+    }
+
+    // GTK answers with the standard buttons even when they are renamed
+    match dialog.show().await {
+        MessageDialogResult::Yes => (true, Some(PermissionDecision::Allow)),
+        MessageDialogResult::No => (false, Some(PermissionDecision::Deny)),
+        MessageDialogResult::Custom(button) if button == ALLOW => {
+            (true, Some(PermissionDecision::Allow))
+        }
+        MessageDialogResult::Custom(button) if button == BLOCK => {
+            (false, Some(PermissionDecision::Deny))
+        }
+        _ => (false, None),
+    }
+}
+
+/// Decides a request right away, or keeps it and asks the user over the
+/// window of the webview labeled `label`.
+#[cfg(any(target_os = "linux", windows))]
+fn broker<R: Runtime>(
+    app: &AppHandle<R>,
+    label: &str,
+    origin: &str,
+    permissions: Vec<MediaPermission>,
+    request: PlatformRequest,
+) {
+    let remembered = settings::current(app).permissions;
+    if let Some(allow) = decide(origin, &permissions, &remembered) {
+        complete(request, allow);
+        return;
+    }
+    let Some(broker) = app.try_state::<PermissionBroker>() else {
+        complete(request, false);
+        return;
+    };
+
+    let id = broker.next_id.fetch_add(1, Ordering::SeqCst);
+    PLATFORM_REQUESTS.with(|requests| requests.borrow_mut().insert(id, request));
+
+    log::info!("Asking for {:?}", permissions);
+    let app = app.clone();
+    let window = app.get_webview(label).map(|webview| webview.window());
+    tauri::async_runtime::spawn(async move {
+        let (allow, decision) = ask(window, &permissions).await;
+        answer(&app, id, &permissions, allow, decision);
+    });
+}
+
+/// Completes the request `id` with the user's answer, remembering `decision`
+/// for the next requests.
+#[cfg(any(target_os = "linux", windows))]
+fn answer<R: Runtime>(
+    app: &AppHandle<R>,
+    id: u32,
+    permissions: &[MediaPermission],
+    allow: bool,
+    decision: Option<PermissionDecision>,
+) {
+    if let (Some(decision), Some(store)) = (decision, app.try_state::<SettingsStore>()) {
+        let mut settings = store.get();
+        for permission in permissions {
+            settings.permissions.set(*permission, decision);
+        }
+        settings::apply(app, &store, settings);
+    }
+
+    let result = app.run_on_main_thread(move || {
+        if let Some(request) = PLATFORM_REQUESTS.with(|requests| requests.borrow_mut().remove(&id))
+        {
+            complete(request, allow);
+        }
+    });
+    if let Err(e) = result {
+        log::warn!("Failed to answer the permission request: {}", e);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn complete(request: PlatformRequest, allow: bool) {
+    use webkit2gtk::PermissionRequestExt;
+
+    if allow {
+        request.allow();
+    } else {
+        request.deny();
+    }
+}
+
+#[cfg(windows)]
+fn complete(request: PlatformRequest, allow: bool) {
+    use webview2_com::Microsoft::Web::WebView2::Win32::{
+        COREWEBVIEW2_PERMISSION_STATE_ALLOW, COREWEBVIEW2_PERMISSION_STATE_DENY,
+    };
+
+    let state = if allow {
+        COREWEBVIEW2_PERMISSION_STATE_ALLOW
+    } else {
+        COREWEBVIEW2_PERMISSION_STATE_DENY
+    };
+    // SAFETY: called on the main thread, where WebView2 raised the request
+    unsafe {
+        let _ = request.args.SetState(state);
+        let _ = request.deferral.Complete();
+    }
+}
+
+/// Routes the camera, microphone and screen requests of a Messenger webview
+/// through the broker. WebKitGTK denies them by default, WebView2 asks with
+/// its own prompt and forgets. On macOS WebKit asks the system, which
+/// remembers the answer for the whole app.
+#[cfg(target_os = "linux")]
+pub fn attach<R: Runtime>(webview: &Webview<R>) {
+    use webkit2gtk::glib::prelude::*;
+    use webkit2gtk::{UserMediaPermissionRequest, UserMediaPermissionRequestExt, WebViewExt};
+
+    let app = webview.app_handle().clone();
+    let label = webview.label().to_string();
+
+    let result = webview.with_webview(move |platform| {
+        platform
+            .inner()
+            .connect_permission_request(move |view, request| {
+                let Some(media) = request.downcast_ref::<UserMediaPermissionRequest>() else {
+                    return false;
+                };
+
+                // SAFETY: `media` is a live user media request for the whole call
+                let is_screen = unsafe {
+                    webkit2gtk::ffi::webkit_user_media_permission_is_for_display_device(
+                        media.as_ptr(),
+                    ) != 0
+                };
+                let permissions = if is_screen {
+                    vec![MediaPermission::Screen]
+                } else {
+                    let mut permissions = Vec::new();
+                    if media.is_for_video_device() {
+                        permissions.push(MediaPermission::Camera);
+                    }
+                    if media.is_for_audio_device() {
+                        permissions.push(MediaPermission::Microphone);
+                    }
+                    permissions
+                };
+
+                let origin = view.uri().map(|uri| uri.to_string()).unwrap_or_default();
+                broker(&app, &label, &origin, permissions, request.clone());
+                true
+            });
+    });
+
+    if let Err(e) = result {
+        log::warn!("Failed to attach the permission broker: {}", e);
+    }
+}
+
+#[cfg(windows)]
+pub fn attach<R: Runtime>(webview: &Webview<R>) {
+    use webview2_com::Microsoft::Web::WebView2::Win32::{
+        COREWEBVIEW2_PERMISSION_KIND, COREWEBVIEW2_PERMISSION_KIND_CAMERA,
+        COREWEBVIEW2_PERMISSION_KIND_MICROPHONE,
+    };
+    use webview2_com::PermissionRequestedEventHandler;
+    use windows::core::PWSTR;
+
+    let app = webview.app_handle().clone();
+    let label = webview.label().to_string();
+
+    let result = webview.with_webview(move |platform| {
+        let handler = PermissionRequestedEventHandler::create(Box::new(move |_, args| {
+            let Some(args) = args else {
+                return Ok(());
+            };
+
+            let mut kind = COREWEBVIEW2_PERMISSION_KIND::default();
+            let mut uri = PWSTR::null();
+            // SAFETY: WebView2 keeps `args` alive while the handler runs
+            unsafe {
+                args.PermissionKind(&mut kind)?;
+                args.Uri(&mut uri)?;
+            }
+            let origin = webview2_com::take_pwstr(uri);
+
+            // Screen sharing goes through WebView2's own picker instead
+            let permission = match kind {
+                COREWEBVIEW2_PERMISSION_KIND_CAMERA => MediaPermission::Camera,
+                COREWEBVIEW2_PERMISSION_KIND_MICROPHONE => MediaPermission::Microphone,
+                _ => return Ok(()),
+            };
+
+            // SAFETY: as above, and the deferral keeps the request open afterwards
+            let deferral = unsafe { args.GetDeferral()? };
+            broker(
+                &app,
+                &label,
+                &origin,
+                vec![permission],
+                PlatformRequest { args, deferral },
+            );
+            Ok(())
+        }));
+
+        let mut token = 0;
+        // SAFETY: the controller belongs to this webview and outlives the handler
+        unsafe {
+            if let Ok(core) = platform.controller().CoreWebView2() {
+                let _ = core.add_PermissionRequested(&handler, &mut token);
+            }
+        }
+    });
+
+    if let Err(e) = result {
+        log::warn!("Failed to attach the permission broker: {}", e);
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+pub fn attach<R: Runtime>(_webview: &Webview<R>) {}
+
+// =========================================================================
+//                               COMMANDS
+// =========================================================================
+
+/// Changes a remembered decision from the settings. Messenger's pages can call
+/// this too, so it can only block or forget: allowing is left to the prompt.
+#[tauri::command]
+pub fn set_media_permission<R: Runtime>(
+    app: AppHandle<R>,
+    store: tauri::State<'_, SettingsStore>,
+    permission: MediaPermission,
+    decision: PermissionDecision,
+) -> Result<(), String> {
+    if decision == PermissionDecision::Allow {
+        return Err("Allow it when Messenger asks for it instead".into());
+    }
+
+    let mut settings = store.get();
+    settings.permissions.set(permission, decision);
+    settings::apply(&app, &store, settings);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use MediaPermission::{Camera, Microphone, Screen};
+
+    const MESSENGER: &str = "https://www.messenger.com";
+
+    fn remembered(camera: PermissionDecision, microphone: PermissionDecision) -> MediaPermissions {
+        MediaPermissions {
+            camera,
+            microphone,
+            ..MediaPermissions::default()
+        }
+    }
+
+    #[test]
+    fn decide_denies_other_origins_and_empty_requests() {
+        let all = remembered(PermissionDecision::Allow, PermissionDecision::Allow);
+        assert_eq!(decide("https://example.com", &[Camera], &all), Some(false));
+        assert_eq!(
+            decide("http://www.messenger.com", &[Camera], &all),
+            Some(false)
+        );
+        assert_eq!(decide("not an origin", &[Camera], &all), Some(false));
+        assert_eq!(decide(MESSENGER, &[], &all), Some(false));
+    }
+
+    #[test]
+    fn decide_follows_the_remembered_decisions() {
+        use PermissionDecision::{Allow, Ask, Deny};
+
+        assert_eq!(
+            decide(MESSENGER, &[Camera], &remembered(Allow, Ask)),
+            Some(true)
+        );
+        assert_eq!(
+            decide(MESSENGER, &[Camera], &remembered(Deny, Allow)),
+            Some(false)
+        );
+        assert_eq!(decide(MESSENGER, &[Camera], &remembered(Ask, Allow)), None);
+        assert_eq!(
+            decide(MESSENGER, &[Screen], &remembered(Allow, Allow)),
+            None
+        );
+    }
+
+    #[test]
+    fn decide_needs_every_permission_of_a_request() {
+        use PermissionDecision::{Allow, Ask, Deny};
+
+        let both = [Camera, Microphone];
+        assert_eq!(
+            decide(MESSENGER, &both, &remembered(Allow, Allow)),
+            Some(true)
+        );
+        assert_eq!(decide(MESSENGER, &both, &remembered(Allow, Ask)), None);
+        assert_eq!(
+            decide(MESSENGER, &both, &remembered(Ask, Deny)),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn describe_lists_the_permissions() {
+        assert_eq!(describe(&[]), "");
+        assert_eq!(describe(&[Camera]), "camera");
+        assert_eq!(describe(&[Camera, Microphone]), "camera and microphone");
+        assert_eq!(
+            describe(&[Camera, Microphone, Screen]),
+            "camera, microphone and screen"
+        );
+    }
+}
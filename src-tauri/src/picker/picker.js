@@ -0,0 +1,54 @@
+// Screen share picker, see screen_share.rs
+const invoke = window.__TAURI__.core.invoke;
+
+const loading = document.getElementById('share-loading');
+const cancel = document.getElementById('share-cancel');
+
+// Closes the picker with the chosen source id, or null when cancelled
+const choose = (source) => invoke('choose_share_source', { source });
+
+const render = (sources) => {
+    loading.hidden = true;
+
+    ['screen', 'window'].forEach((kind) => {
+        const grid = document.getElementById(kind === 'screen' ? 'share-screens' : 'share-windows');
+        const matching = sources.filter((source) => source.kind === kind);
+        document.getElementById(`${grid.id}-title`).hidden = matching.length === 0;
+
+        grid.innerHTML = '';
+        matching.forEach((source) => {
+            const item = document.createElement('button');
+            item.type = 'button';
+            item.className = 'share-source';
+            item.title = source.name;
+
+            const preview = document.createElement(source.thumbnail ? 'img' : 'div');
+            preview.className = 'share-thumbnail';
+            if (source.thumbnail) preview.src = source.thumbnail;
+            preview.setAttribute('aria-hidden', 'true');
+
+            const name = document.createElement('span');
+            name.className = 'share-name';
+            name.textContent = source.name;
+
+            item.append(preview, name);
+            item.addEventListener('click', () => choose(source.id));
+            grid.appendChild(item);
+        });
+    });
+
+    document.querySelector('.share-source, #share-cancel').focus();
+};
+
+cancel.addEventListener('click', () => choose(null));
+document.addEventListener('keydown', (e) => {
+    if (e.key !== 'Escape') return;
+    e.preventDefault();
+    choose(null);
+});
+
+invoke('share_picker_sources')
+    .then(render)
+    .catch((err) => {
+        loading.textContent = `Could not list what to share: ${err}`;
+    });
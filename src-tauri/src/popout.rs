@@ -0,0 +1,73 @@
+use tauri::menu::{Menu, MenuEvent, MenuItem};
+use tauri::{AppHandle, Manager, Runtime, Webview};
+
+use crate::accounts::{self, AccountManager};
+use crate::url_policy;
+
+/// Prefix of the ids of the conversation menu items, followed by the label of
+/// the webview and the path of the conversation.
+const MENU_ID_PREFIX: &str = "popout";
+
+/// Opens the conversation at `path` of the account shown in the webview
+/// labeled `label` in a window of its own.
+fn pop_out<R: Runtime>(app: &AppHandle<R>, label: &str, path: &str) -> Result<(), String> {
+    let url = url_policy::conversation_url(path).ok_or("Not a conversation")?;
+    let account = accounts::account_id_for_label(label)
+        .and_then(|id| app.state::<AccountManager>().get(&id))
+        .ok_or("Unknown account")?;
+
+    log::info!("Popping out {} of {}", url.path(), account.id);
+    accounts::open_conversation_window(app, &account, url)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Handles the items of the conversation menu, registered once for the whole app.
+pub fn on_menu_event<R: Runtime>(app: &AppHandle<R>, event: MenuEvent) {
+    let mut parts = event.id().as_ref().splitn(3, ':');
+    let (Some(MENU_ID_PREFIX), Some(label), Some(path)) =
+        (parts.next(), parts.next(), parts.next())
+    else {
+        return;
+    };
+
+    if let Err(e) = pop_out(app, label, path) {
+        log::warn!("Failed to pop out the conversation: {}", e);
+    }
+}
+
+// =========================================================================
+//                               COMMANDS
+// =========================================================================
+
+/// Opens the conversation at `path` (`/t/<id>` or `/e2ee/t/<id>`) in a compact
+/// window of its own, sharing the session of the calling webview.
+#[tauri::command]
+pub async fn pop_out_conversation<R: Runtime>(
+    app: AppHandle<R>,
+    webview: Webview<R>,
+    path: String,
+) -> Result<(), String> {
+    pop_out(&app, webview.label(), &path)
+}
+
+/// Shows the menu of a conversation in the chat list at the pointer.
+#[tauri::command]
+pub fn show_conversation_menu<R: Runtime>(webview: Webview<R>, path: String) -> Result<(), String> {
+    if url_policy::conversation_url(&path).is_none() {
+        return Err("Not a conversation".into());
+    }
+
+    let window = webview.window();
+    let open = MenuItem::with_id(
+        &window,
+        format!("{}:{}:{}", MENU_ID_PREFIX, webview.label(), path),
+        "Open in new window",
+        true,
+        None::<&str>,
+    )
+    .map_err(|e| e.to_string())?;
+    let menu = Menu::with_items(&window, &[&open]).map_err(|e| e.to_string())?;
+
+    window.popup_menu(&menu).map_err(|e| e.to_string())
+}
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::cli::Options;
+
+const LAUNCH_STATE_FILE: &str = "launch-state.json";
+
+/// Launches that never became healthy before safe mode kicks in on its own.
+const MAX_FAILED_LAUNCHES: u32 = 3;
+
+/// How long a launch has to stay up before it counts as healthy.
+const HEALTHY_AFTER_SECS: u64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SafeModeReason {
+    /// `--safe-mode` was passed.
+    Flag,
+    /// Shift was held while Messterial started.
+    ModifierKey,
+    /// The previous launches crashed or never finished starting.
+    FailedLaunches,
+}
+
+/// Whether this launch runs without any injected customizations, with native
+/// window decorations so Messenger stays usable when a layout change breaks
+/// the injected CSS/JS.
+#[derive(Debug, Clone, Copy)]
+pub struct SafeMode {
+    pub reason: Option<SafeModeReason>,
+}
+
+impl SafeMode {
+    pub fn is_active(&self) -> bool {
+        self.reason.is_some()
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LaunchState {
+    failed_launches: u32,
+}
+
+fn launch_state_path<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<PathBuf> {
+    Ok(app.path().app_local_data_dir()?.join(LAUNCH_STATE_FILE))
+}
+
+fn read_launch_state<R: Runtime>(app: &AppHandle<R>) -> LaunchState {
+    launch_state_path(app)
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+fn write_launch_state<R: Runtime>(app: &AppHandle<R>, state: &LaunchState) {
+    let Ok(path) = launch_state_path(app) else {
+        return;
+    };
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    if let Ok(raw) = serde_json::to_string(state) {
+        if let Err(e) = std::fs::write(&path, raw) {
+            log::error!("Failed to save launch state: {}", e);
+        }
+    }
+}
+
+/// Decides whether this launch runs in safe mode, and records it as pending
+/// until [`mark_healthy`] is called.
+pub fn detect<R: Runtime>(app: &AppHandle<R>, options: &Options) -> SafeMode {
+    let mut state = read_launch_state(app);
+
+    let reason = if options.safe_mode {
+        Some(SafeModeReason::Flag)
+    } else if is_shift_held() {
+        Some(SafeModeReason::ModifierKey)
+    } else if state.failed_launches >= MAX_FAILED_LAUNCHES {
+        Some(SafeModeReason::FailedLaunches)
+    } else {
+        None
+    };
+
+    if let Some(reason) = reason {
+        log::warn!("Starting in safe mode ({:?})", reason);
+    }
+
+    // Counts as failed unless it gets marked healthy later on
+    state.failed_launches += 1;
+    write_launch_state(app, &state);
+
+    SafeMode { reason }
+}
+
+/// Resets the failed launch counter once the app is up and running.
+pub fn mark_healthy<R: Runtime>(app: &AppHandle<R>) {
+    if read_launch_state(app).failed_launches != 0 {
+        write_launch_state(app, &LaunchState::default());
+    }
+}
+
+/// Restarts the app on purpose, so the launch it leaves doesn't count as
+/// failed. `AppHandle::restart` exits without `RunEvent::Exit`.
+pub fn restart<R: Runtime>(app: &AppHandle<R>) -> ! {
+    mark_healthy(app);
+    app.restart()
+}
+
+/// Waits for the app to stay up for a while before calling it healthy.
+pub fn spawn_health_check<R: Runtime>(app: AppHandle<R>) {
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_secs(HEALTHY_AFTER_SECS));
+        mark_healthy(&app);
+    });
+}
+
+#[cfg(target_os = "windows")]
+fn is_shift_held() -> bool {
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetAsyncKeyState, VK_SHIFT};
+
+    // The most significant bit is set while the key is down
+    unsafe { GetAsyncKeyState(VK_SHIFT as i32) as u16 & 0x8000 != 0 }
+}
+
+#[cfg(target_os = "macos")]
+fn is_shift_held() -> bool {
+    const COMBINED_SESSION_STATE: i32 = 0;
+    const SHIFT_MASK: u64 = 0x0002_0000;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventSourceFlagsState(state_id: i32) -> u64;
+    }
+
+    unsafe { CGEventSourceFlagsState(COMBINED_SESSION_STATE) & SHIFT_MASK != 0 }
+}
+
+#[cfg(target_os = "linux")]
+fn is_shift_held() -> bool {
+    use gtk::gdk;
+
+    gdk::Display::default()
+        .and_then(|display| gdk::Keymap::for_display(&display))
+        .map(|keymap| keymap.modifier_state() & gdk::ModifierType::SHIFT_MASK.bits() != 0)
+        .unwrap_or_default()
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+fn is_shift_held() -> bool {
+    false
+}
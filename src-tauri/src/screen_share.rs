@@ -0,0 +1,466 @@
+use base64::Engine;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::http::{header, Request, Response as HttpResponse, StatusCode};
+use tauri::ipc::Response;
+use tauri::{
+    AppHandle, Manager, Runtime, UriSchemeContext, Webview, WebviewUrl, WebviewWindowBuilder,
+    WindowEvent,
+};
+use xcap::image::{codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage, RgbaImage};
+use xcap::{Monitor, Window};
+
+use crate::settings::{self, MediaPermission, PermissionDecision};
+use crate::{cli, url_policy};
+
+/// URI scheme the picker is served from. It's a local page the Messenger pages
+/// can't reach, so the previews of the screens and windows never get to them.
+pub const SCHEME: &str = "messterial-share";
+
+const PICKER_HTML: &str = include_str!("picker/index.html");
+const PICKER_CSS: &str = include_str!("picker/picker.css");
+const PICKER_JS: &str = include_str!("picker/picker.js");
+
+const PICKER_LABEL: &str = "share-picker";
+const PICKER_TITLE: &str = "Choose what to share";
+
+/// Profile of the picker window, which has nothing to keep. WebView2 can't
+/// share a profile with the Messenger webviews, created with other arguments.
+const PROFILE_DIR: &str = "share-picker";
+
+/// Frames per second sent to the page while sharing.
+const FRAME_RATE: u32 = 15;
+
+/// Largest width of a shared frame, bigger screens are scaled down.
+const MAX_FRAME_WIDTH: u32 = 1920;
+
+const THUMBNAIL_WIDTH: u32 = 320;
+
+const JPEG_QUALITY: u8 = 75;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceKind {
+    Screen,
+    Window,
+}
+
+/// Something that can be shared, listed in the picker.
+#[derive(Debug, Clone, Serialize)]
+pub struct ShareSource {
+    /// `screen:<id>` or `window:<id>`.
+    pub id: String,
+    pub kind: SourceKind,
+    pub name: String,
+    /// JPEG data URL, when the platform lets us capture the source.
+    pub thumbnail: Option<String>,
+}
+
+struct Session {
+    /// Label of the window that is sharing.
+    window: String,
+    running: Arc<AtomicBool>,
+    /// Latest frame as JPEG, taken by the page.
+    frame: Arc<Mutex<Option<Vec<u8>>>>,
+}
+
+/// The open picker.
+struct Picker {
+    /// Label of the window the source is picked for.
+    window: String,
+    /// Gets the id of the picked source, or `None` when cancelled.
+    answer: mpsc::Sender<Option<String>>,
+}
+
+/// A source the user picked, until the page starts sharing it.
+struct Grant {
+    window: String,
+    source: String,
+}
+
+/// The picker and the ongoing screen shares.
+#[derive(Default)]
+pub struct ScreenShare {
+    next_id: AtomicU32,
+    picker: Mutex<Option<Picker>>,
+    /// By one-time token, handed to the page that asked for the picker.
+    grants: Mutex<HashMap<u32, Grant>>,
+    sessions: Mutex<HashMap<u32, Session>>,
+}
+
+/// Whether sources can be listed and captured here. Wayland only allows it
+/// through the ScreenCast portal, which WebKitGTK uses on its own.
+fn can_capture() -> bool {
+    !(cfg!(target_os = "linux") && std::env::var_os("WAYLAND_DISPLAY").is_some())
+}
+
+fn encode(image: RgbaImage, max_width: u32) -> Option<Vec<u8>> {
+    let mut image = DynamicImage::ImageRgba8(image);
+    if image.width() > max_width {
+        let height = image.height() * max_width / image.width();
+        image = image.resize(max_width, height, FilterType::Triangle);
+    }
+
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY)
+        .encode_image(&image.to_rgb8())
+        .ok()?;
+    Some(jpeg)
+}
+
+fn thumbnail(image: xcap::XCapResult<RgbaImage>) -> Option<String> {
+    let jpeg = encode(image.ok()?, THUMBNAIL_WIDTH)?;
+    Some(format!(
+        "data:image/jpeg;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(jpeg)
+    ))
+}
+
+/// Whether screen sharing is allowed, or may be asked for with the picker.
+fn is_blocked<R: Runtime>(app: &AppHandle<R>) -> bool {
+    settings::current(app)
+        .permissions
+        .get(MediaPermission::Screen)
+        == PermissionDecision::Deny
+}
+
+fn sources() -> Vec<ShareSource> {
+    let mut sources = Vec::new();
+
+    for (index, monitor) in Monitor::all().unwrap_or_default().iter().enumerate() {
+        let Ok(id) = monitor.id() else {
+            continue;
+        };
+        let name = match monitor.name() {
+            Ok(name) if !name.is_empty() => name,
+            _ => format!("Screen {}", index + 1),
+        };
+        sources.push(ShareSource {
+            id: format!("screen:{}", id),
+            kind: SourceKind::Screen,
+            name,
+            thumbnail: thumbnail(monitor.capture_image()),
+        });
+    }
+
+    for window in Window::all().unwrap_or_default() {
+        let (Ok(id), Ok(title)) = (window.id(), window.title()) else {
+            continue;
+        };
+        if title.is_empty() || window.is_minimized().unwrap_or(false) {
+            continue;
+        }
+        if title == PICKER_TITLE && window.pid().ok() == Some(std::process::id()) {
+            continue;
+        }
+        let name = match window.app_name() {
+            Ok(app) if !app.is_empty() && app != title => format!("{} - {}", title, app),
+            _ => title,
+        };
+        sources.push(ShareSource {
+            id: format!("window:{}", id),
+            kind: SourceKind::Window,
+            name,
+            thumbnail: thumbnail(window.capture_image()),
+        });
+    }
+
+    sources
+}
+
+/// Captures the source with the id from [`ShareSource::id`].
+fn capture(source: &str) -> Result<RgbaImage, String> {
+    let (kind, id) = source.split_once(':').ok_or("Unknown source")?;
+    let id: u32 = id.parse().map_err(|_| "Unknown source")?;
+
+    let image = match kind {
+        "screen" => Monitor::all()
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .find(|monitor| monitor.id().ok() == Some(id))
+            .ok_or("The screen is gone")?
+            .capture_image(),
+        "window" => Window::all()
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .find(|window| window.id().ok() == Some(id))
+            .ok_or("The window was closed")?
+            .capture_image(),
+        _ => return Err("Unknown source".into()),
+    };
+    image.map_err(|e| e.to_string())
+}
+
+/// Keeps the latest frame of `source` in `frame` until `running` is cleared.
+fn spawn_capture(source: String, running: Arc<AtomicBool>, frame: Arc<Mutex<Option<Vec<u8>>>>) {
+    let interval = Duration::from_secs(1) / FRAME_RATE;
+
+    std::thread::spawn(move || {
+        while running.load(Ordering::SeqCst) {
+            let started = Instant::now();
+            match capture(&source) {
+                Ok(image) => {
+                    if let Some(jpeg) = encode(image, MAX_FRAME_WIDTH) {
+                        *frame.lock().unwrap() = Some(jpeg);
+                    }
+                }
+                Err(e) => {
+                    log::info!("Screen share of {} ended: {}", source, e);
+                    running.store(false, Ordering::SeqCst);
+                }
+            }
+            std::thread::sleep(interval.saturating_sub(started.elapsed()));
+        }
+    });
+}
+
+/// Serves the picker page.
+pub fn protocol<R: Runtime>(
+    _context: UriSchemeContext<'_, R>,
+    request: Request<Vec<u8>>,
+) -> HttpResponse<Cow<'static, [u8]>> {
+    let (content_type, body) = match request.uri().path() {
+        "/" | "/index.html" => ("text/html", PICKER_HTML),
+        "/picker.css" => ("text/css", PICKER_CSS),
+        "/picker.js" => ("text/javascript", PICKER_JS),
+        _ => {
+            return HttpResponse::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Cow::Borrowed(&[][..]))
+                .unwrap_or_default();
+        }
+    };
+
+    HttpResponse::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .body(Cow::Borrowed(body.as_bytes()))
+        .unwrap_or_default()
+}
+
+/// Opens the picker over the window labeled `label`. Its answer goes to the
+/// returned receiver, `None` as well when the picker is closed.
+fn open_picker<R: Runtime>(
+    app: &AppHandle<R>,
+    state: &ScreenShare,
+    label: &str,
+) -> Result<mpsc::Receiver<Option<String>>, String> {
+    let mut picker = state.picker.lock().unwrap();
+    if picker.is_some() {
+        if let Some(window) = app.get_webview_window(PICKER_LABEL) {
+            let _ = window.set_focus();
+        }
+        return Err("Already choosing what to share".into());
+    }
+
+    let profile = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|e| e.to_string())?
+        .join(PROFILE_DIR);
+    let url = url_policy::app_page_url(SCHEME);
+    let mut builder = WebviewWindowBuilder::new(app, PICKER_LABEL, WebviewUrl::CustomProtocol(url))
+        .title(PICKER_TITLE)
+        .inner_size(680.0, 520.0)
+        .min_inner_size(360.0, 280.0)
+        .center()
+        .devtools(cli::devtools_enabled(app))
+        .data_directory(profile);
+    if let Some(parent) = app.get_webview_window(label) {
+        builder = builder.parent(&parent).map_err(|e| e.to_string())?;
+    }
+    let window = builder.build().map_err(|e| e.to_string())?;
+
+    let handle = app.clone();
+    window.on_window_event(move |event| {
+        if let WindowEvent::Destroyed = event {
+            let picker = handle.state::<ScreenShare>().picker.lock().unwrap().take();
+            if let Some(picker) = picker {
+                let _ = picker.answer.send(None);
+            }
+        }
+    });
+
+    let (answer, receiver) = mpsc::channel();
+    *picker = Some(Picker {
+        window: label.to_string(),
+        answer,
+    });
+    Ok(receiver)
+}
+
+/// Whether `webview` is the picker, the only page that sees the sources.
+fn is_picker<R: Runtime>(webview: &Webview<R>) -> bool {
+    webview.window().label() == PICKER_LABEL
+}
+
+/// Stops everything shared by the window labeled `label`, e.g. once its call ended.
+pub fn stop_window<R: Runtime>(app: &AppHandle<R>, label: &str) {
+    let Some(state) = app.try_state::<ScreenShare>() else {
+        return;
+    };
+    state
+        .grants
+        .lock()
+        .unwrap()
+        .retain(|_, grant| grant.window != label);
+    state.sessions.lock().unwrap().retain(|_, session| {
+        let keep = session.window != label;
+        if !keep {
+            session.running.store(false, Ordering::SeqCst);
+        }
+        keep
+    });
+}
+
+// =========================================================================
+//                               COMMANDS
+// =========================================================================
+
+/// Lets the user pick what to share in the picker window. Returns a one-time
+/// token for [`start_screen_share`], or `None` when the page should use the
+/// platform's own picker instead.
+#[tauri::command]
+pub async fn pick_share_source<R: Runtime>(
+    app: AppHandle<R>,
+    webview: Webview<R>,
+) -> Result<Option<u32>, String> {
+    if is_blocked(&app) {
+        return Err("Screen sharing is blocked in the settings".into());
+    }
+    if !can_capture() {
+        return Ok(None);
+    }
+
+    let state = app.state::<ScreenShare>();
+    let label = webview.window().label().to_string();
+    let receiver = open_picker(&app, &state, &label)?;
+
+    let picked = tauri::async_runtime::spawn_blocking(move || receiver.recv().ok().flatten())
+        .await
+        .map_err(|e| e.to_string())?;
+    let Some(source) = picked else {
+        return Err("Screen sharing was cancelled".into());
+    };
+
+    let token = state.next_id.fetch_add(1, Ordering::SeqCst);
+    state.grants.lock().unwrap().insert(
+        token,
+        Grant {
+            window: label,
+            source,
+        },
+    );
+    Ok(Some(token))
+}
+
+/// Sources for the picker, with their previews.
+#[tauri::command]
+pub async fn share_picker_sources<R: Runtime>(
+    webview: Webview<R>,
+) -> Result<Vec<ShareSource>, String> {
+    if !is_picker(&webview) {
+        return Err("Only the picker lists what can be shared".into());
+    }
+    tauri::async_runtime::spawn_blocking(sources)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Answers the picker with the id of the picked source, or `None` when the
+/// user cancelled, and closes it.
+#[tauri::command]
+pub fn choose_share_source<R: Runtime>(
+    webview: Webview<R>,
+    state: tauri::State<'_, ScreenShare>,
+    source: Option<String>,
+) -> Result<(), String> {
+    if !is_picker(&webview) {
+        return Err("Only the picker chooses what to share".into());
+    }
+
+    if let Some(picker) = state.picker.lock().unwrap().take() {
+        log::info!("Picked {:?} to share in {}", source, picker.window);
+        let _ = picker.answer.send(source);
+    }
+    webview.window().close().map_err(|e| e.to_string())
+}
+
+/// Starts capturing the source picked for `token`, returning the id to take
+/// the frames with.
+#[tauri::command]
+pub fn start_screen_share<R: Runtime>(
+    app: AppHandle<R>,
+    webview: Webview<R>,
+    state: tauri::State<'_, ScreenShare>,
+    token: u32,
+) -> Result<u32, String> {
+    let label = webview.window().label().to_string();
+    let Some(grant) = state.grants.lock().unwrap().remove(&token) else {
+        return Err("Nothing was picked to share".into());
+    };
+    if grant.window != label {
+        return Err("Nothing was picked to share".into());
+    }
+    if is_blocked(&app) {
+        return Err("Screen sharing is blocked in the settings".into());
+    }
+
+    let id = state.next_id.fetch_add(1, Ordering::SeqCst);
+    let running = Arc::new(AtomicBool::new(true));
+    let frame = Arc::new(Mutex::new(None));
+
+    spawn_capture(grant.source, running.clone(), frame.clone());
+    state.sessions.lock().unwrap().insert(
+        id,
+        Session {
+            window: label,
+            running,
+            frame,
+        },
+    );
+    Ok(id)
+}
+
+/// The newest frame of a share as JPEG, empty when there is no new one yet.
+#[tauri::command]
+pub fn screen_share_frame<R: Runtime>(
+    webview: Webview<R>,
+    state: tauri::State<'_, ScreenShare>,
+    id: u32,
+) -> Result<Response, String> {
+    let mut sessions = state.sessions.lock().unwrap();
+    let Some(session) = sessions
+        .get(&id)
+        .filter(|session| session.window == webview.window().label())
+    else {
+        return Err("The screen share ended".into());
+    };
+    if !session.running.load(Ordering::SeqCst) {
+        sessions.remove(&id);
+        return Err("The screen share ended".into());
+    }
+
+    let frame = session.frame.lock().unwrap().take().unwrap_or_default();
+    Ok(Response::new(frame))
+}
+
+#[tauri::command]
+pub fn stop_screen_share<R: Runtime>(
+    webview: Webview<R>,
+    state: tauri::State<'_, ScreenShare>,
+    id: u32,
+) {
+    let mut sessions = state.sessions.lock().unwrap();
+    if sessions
+        .get(&id)
+        .is_some_and(|session| session.window == webview.window().label())
+    {
+        if let Some(session) = sessions.remove(&id) {
+            session.running.store(false, Ordering::SeqCst);
+        }
+    }
+}
@@ -0,0 +1,429 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+
+use crate::{injection, safe_mode, shortcuts};
+
+const SETTINGS_FILE: &str = "settings.json";
+
+/// A piece of the injected customizations that can be turned off in settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    Layout,
+    Titlebar,
+    Bloat,
+    Bubbles,
+    Scrollbars,
+    Analytics,
+    Links,
+    Badges,
+    Shortcuts,
+    Switcher,
+    Downloads,
+    Media,
+    Popout,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Features {
+    /// Floating navigation and chat cards.
+    pub layout: bool,
+    /// Custom titlebar with window controls. Native decorations are used without it.
+    pub titlebar: bool,
+    /// Hides the inbox switcher, banner and other Messenger bloat.
+    pub bloat: bool,
+    /// Rounded chat bubbles.
+    pub bubbles: bool,
+    /// Thin translucent scrollbars.
+    pub scrollbars: bool,
+    /// Drops analytics and logging requests.
+    pub analytics: bool,
+    /// Opens external links in the default browser.
+    pub links: bool,
+    /// Unread count badge on the window icon.
+    pub badges: bool,
+    /// Keyboard shortcuts from the keymap, global ones included.
+    pub shortcuts: bool,
+    /// Quick switcher for chats and commands.
+    pub switcher: bool,
+    /// Downloads panel in the titlebar. Files are saved without it as well.
+    pub downloads: bool,
+    /// Opens photos and videos in the media viewer instead of a browser window.
+    pub media: bool,
+    /// Pops conversations out into their own windows.
+    pub popout: bool,
+    /// The animations toggle of older versions, see [`Settings::migrate`].
+    #[serde(rename = "animations", skip_serializing)]
+    legacy_animations: Option<bool>,
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Self {
+            layout: true,
+            titlebar: true,
+            bloat: true,
+            bubbles: true,
+            scrollbars: true,
+            analytics: true,
+            links: true,
+            badges: true,
+            shortcuts: true,
+            switcher: true,
+            downloads: true,
+            media: true,
+            popout: true,
+            legacy_animations: None,
+        }
+    }
+}
+
+impl Features {
+    pub fn is_enabled(&self, feature: Feature) -> bool {
+        match feature {
+            Feature::Layout => self.layout,
+            Feature::Titlebar => self.titlebar,
+            Feature::Bloat => self.bloat,
+            Feature::Bubbles => self.bubbles,
+            Feature::Scrollbars => self.scrollbars,
+            Feature::Analytics => self.analytics,
+            Feature::Links => self.links,
+            Feature::Badges => self.badges,
+            Feature::Shortcuts => self.shortcuts,
+            Feature::Switcher => self.switcher,
+            Feature::Downloads => self.downloads,
+            Feature::Media => self.media,
+            Feature::Popout => self.popout,
+        }
+    }
+}
+
+/// Keymap in accelerator syntax, e.g. `CmdOrCtrl+Shift+M`. An empty string
+/// turns a shortcut off.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Shortcuts {
+    /// Global: shows or hides the active window, even when it's in the background.
+    pub toggle_window: String,
+    /// Global: mutes or unmutes Messenger's sounds.
+    pub toggle_mute: String,
+    pub quick_switcher: String,
+    /// Modifiers only, pressed together with 1-9 to open the nth chat.
+    pub jump_to_chat: String,
+    pub open_settings: String,
+    pub hide_window: String,
+    pub zoom_in: String,
+    pub zoom_out: String,
+    pub reset_zoom: String,
+}
+
+impl Default for Shortcuts {
+    fn default() -> Self {
+        Self {
+            toggle_window: "CmdOrCtrl+Shift+M".into(),
+            toggle_mute: "CmdOrCtrl+Shift+U".into(),
+            quick_switcher: "CmdOrCtrl+K".into(),
+            jump_to_chat: "CmdOrCtrl".into(),
+            open_settings: "CmdOrCtrl+Comma".into(),
+            hide_window: "CmdOrCtrl+W".into(),
+            zoom_in: "CmdOrCtrl+Equal".into(),
+            zoom_out: "CmdOrCtrl+Minus".into(),
+            reset_zoom: "CmdOrCtrl+0".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+    /// Black and white with a yellow accent and thicker focus rings.
+    #[serde(rename = "high-contrast")]
+    HighContrast,
+}
+
+/// How much Messterial's own transitions and animations move.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Animation {
+    /// Full, or reduced when the OS asks for reduced motion.
+    #[default]
+    System,
+    Full,
+    /// Fades only, without scaling or bouncing.
+    Reduced,
+    None,
+}
+
+/// Spacing of the cards and the chat list.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Density {
+    Compact,
+    #[default]
+    Comfortable,
+    Spacious,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScrollbarColor {
+    /// Translucent text color.
+    #[default]
+    Neutral,
+    /// Translucent primary color of the palette.
+    Accent,
+}
+
+/// Look of the custom scrollbars, shared by the navigation and chat cards.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scrollbars {
+    /// In pixels.
+    pub width: u32,
+    pub color: ScrollbarColor,
+    /// Only shows the thumb while the pointer is over the scrolled element.
+    pub auto_hide: bool,
+    /// Uses the platform's overlay scrollbars instead, which don't take up space.
+    pub overlay: bool,
+}
+
+impl Default for Scrollbars {
+    fn default() -> Self {
+        Self {
+            width: 6,
+            color: ScrollbarColor::default(),
+            auto_hide: false,
+            overlay: false,
+        }
+    }
+}
+
+/// A device Messenger can ask to use, mostly for calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaPermission {
+    Camera,
+    Microphone,
+    /// Screen sharing.
+    Screen,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionDecision {
+    /// Prompts the next time Messenger asks.
+    #[default]
+    Ask,
+    Allow,
+    Deny,
+}
+
+/// What Messenger may use, remembered from the permission prompts.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MediaPermissions {
+    pub camera: PermissionDecision,
+    pub microphone: PermissionDecision,
+    pub screen: PermissionDecision,
+}
+
+impl MediaPermissions {
+    pub fn get(&self, permission: MediaPermission) -> PermissionDecision {
+        match permission {
+            MediaPermission::Camera => self.camera,
+            MediaPermission::Microphone => self.microphone,
+            MediaPermission::Screen => self.screen,
+        }
+    }
+
+    pub fn set(&mut self, permission: MediaPermission, decision: PermissionDecision) {
+        match permission {
+            MediaPermission::Camera => self.camera = decision,
+            MediaPermission::Microphone => self.microphone = decision,
+            MediaPermission::Screen => self.screen = decision,
+        }
+    }
+}
+
+/// Where attachments are saved, see downloads.rs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Downloads {
+    /// The system's downloads folder when unset.
+    pub folder: Option<PathBuf>,
+    /// Asks for the folder and file name of every download.
+    pub ask: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub theme: Theme,
+    pub density: Density,
+    pub animation: Animation,
+    pub features: Features,
+    pub scrollbars: Scrollbars,
+    pub shortcuts: Shortcuts,
+    pub permissions: MediaPermissions,
+    pub downloads: Downloads,
+}
+
+impl Settings {
+    /// Carries over what older versions saved elsewhere: turning animations
+    /// off in the features became [`Animation::None`].
+    fn migrate(&mut self) {
+        if self.features.legacy_animations.take() == Some(false)
+            && self.animation == Animation::default()
+        {
+            self.animation = Animation::None;
+        }
+    }
+}
+
+/// Holds the settings and persists them in the app config dir.
+pub struct SettingsStore {
+    settings: Mutex<Settings>,
+    path: PathBuf,
+}
+
+impl SettingsStore {
+    pub fn load<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<Self> {
+        let path = app.path().app_config_dir()?.join(SETTINGS_FILE);
+
+        let mut settings = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str::<Settings>(&raw).ok())
+            .unwrap_or_default();
+        settings.migrate();
+
+        Ok(Self {
+            settings: Mutex::new(settings),
+            path,
+        })
+    }
+
+    pub fn get(&self) -> Settings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn set(&self, settings: Settings) {
+        if let Some(dir) = self.path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        match serde_json::to_string_pretty(&settings) {
+            Ok(raw) => {
+                if let Err(e) = std::fs::write(&self.path, raw) {
+                    log::error!("Failed to save settings: {}", e);
+                }
+            }
+            Err(e) => log::error!("Failed to serialize settings: {}", e),
+        }
+        *self.settings.lock().unwrap() = settings;
+    }
+}
+
+/// Current settings, or the defaults before the store is loaded.
+pub fn current<R: Runtime>(app: &AppHandle<R>) -> Settings {
+    app.try_state::<SettingsStore>()
+        .map(|store| store.get())
+        .unwrap_or_default()
+}
+
+/// Saves and applies the settings. Returns whether a restart is needed, since
+/// scripts can't be removed from a loaded page and WebView2 only reads its
+/// arguments when the webview is created.
+pub fn apply<R: Runtime>(app: &AppHandle<R>, store: &SettingsStore, settings: Settings) -> bool {
+    let previous = store.get();
+    store.set(settings.clone());
+    injection::refresh_styles(app);
+
+    if settings.shortcuts != previous.shortcuts
+        || settings.features.shortcuts != previous.features.shortcuts
+    {
+        shortcuts::register_global(app);
+    }
+    let _ = app.emit("settings-changed", &settings);
+
+    injection::scripts_changed(&previous, &settings)
+        || (cfg!(windows) && settings.scrollbars.overlay != previous.scrollbars.overlay)
+}
+
+// =========================================================================
+//                               COMMANDS
+// =========================================================================
+
+#[tauri::command]
+pub fn get_settings(store: tauri::State<'_, SettingsStore>) -> Settings {
+    store.get()
+}
+
+/// Saves the settings from the settings panel. It lives in Messenger's pages,
+/// so what those pages shouldn't pick is kept as it is: the remembered
+/// permissions, see `permissions::set_media_permission`, and the download
+/// folder, see `downloads::choose_download_folder`.
+#[tauri::command]
+pub fn update_settings<R: Runtime>(
+    app: AppHandle<R>,
+    store: tauri::State<'_, SettingsStore>,
+    mut settings: Settings,
+) -> bool {
+    let current = store.get();
+    settings.permissions = current.permissions;
+    settings.downloads.folder = current.downloads.folder;
+    apply(&app, &store, settings)
+}
+
+#[tauri::command]
+pub fn toggle_theme<R: Runtime>(app: AppHandle<R>, store: tauri::State<'_, SettingsStore>) {
+    let mut settings = store.get();
+    settings.theme = match settings.theme {
+        Theme::Dark => Theme::Light,
+        Theme::Light | Theme::HighContrast => Theme::Dark,
+    };
+    apply(&app, &store, settings);
+}
+
+#[tauri::command]
+pub fn restart_app<R: Runtime>(app: AppHandle<R>) {
+    safe_mode::restart(&app);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(raw: &str) -> Settings {
+        let mut settings: Settings = serde_json::from_str(raw).unwrap();
+        settings.migrate();
+        settings
+    }
+
+    #[test]
+    fn migrate_turns_off_animations_disabled_by_older_versions() {
+        let settings = load(r#"{ "features": { "animations": false, "bubbles": false } }"#);
+        assert_eq!(settings.animation, Animation::None);
+        assert!(!settings.features.bubbles);
+
+        let saved = serde_json::to_string(&settings).unwrap();
+        assert!(!saved.contains("\"animations\""));
+        assert!(saved.contains(r#""animation":"none""#));
+    }
+
+    #[test]
+    fn migrate_keeps_the_animation_setting() {
+        assert_eq!(
+            load(r#"{ "features": { "animations": true } }"#).animation,
+            Animation::System
+        );
+        assert_eq!(
+            load(r#"{ "animation": "reduced", "features": { "animations": false } }"#).animation,
+            Animation::Reduced
+        );
+        assert_eq!(load("{}").animation, Animation::System);
+    }
+}
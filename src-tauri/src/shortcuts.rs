@@ -0,0 +1,136 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, Runtime, Webview};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState};
+
+use crate::{accounts, calls, settings};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GlobalAction {
+    ToggleWindow,
+    ToggleMute,
+}
+
+/// Global hotkeys currently registered with the OS, and the mute state they toggle.
+#[derive(Default)]
+pub struct GlobalShortcuts {
+    registered: Mutex<Vec<(Shortcut, GlobalAction)>>,
+    muted: AtomicBool,
+}
+
+/// Registers the global hotkeys from the settings, replacing the previous ones.
+pub fn register_global<R: Runtime>(app: &AppHandle<R>) {
+    let Some(state) = app.try_state::<GlobalShortcuts>() else {
+        return;
+    };
+    let settings = settings::current(app);
+    let shortcuts = settings.shortcuts;
+    let global_shortcut = app.global_shortcut();
+
+    let mut registered = state.registered.lock().unwrap();
+    if let Err(e) = global_shortcut.unregister_all() {
+        log::warn!("Failed to unregister global shortcuts: {}", e);
+    }
+    registered.clear();
+
+    if !settings.features.shortcuts {
+        return;
+    }
+
+    let bindings = [
+        (&shortcuts.toggle_window, GlobalAction::ToggleWindow),
+        (&shortcuts.toggle_mute, GlobalAction::ToggleMute),
+    ];
+    for (accelerator, action) in bindings {
+        if accelerator.is_empty() {
+            continue;
+        }
+
+        let shortcut = match accelerator.parse::<Shortcut>() {
+            Ok(shortcut) => shortcut,
+            Err(e) => {
+                log::warn!("Invalid shortcut '{}': {}", accelerator, e);
+                continue;
+            }
+        };
+        // Usually another app already owns the combination
+        if let Err(e) = global_shortcut.register(shortcut) {
+            log::warn!(
+                "Failed to register global shortcut '{}': {}",
+                accelerator,
+                e
+            );
+            continue;
+        }
+        registered.push((shortcut, action));
+    }
+}
+
+/// Handler of the global shortcut plugin.
+pub fn on_shortcut<R: Runtime>(app: &AppHandle<R>, shortcut: &Shortcut, event: ShortcutEvent) {
+    if event.state() != ShortcutState::Pressed {
+        return;
+    }
+    let Some(state) = app.try_state::<GlobalShortcuts>() else {
+        return;
+    };
+    let action = state
+        .registered
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(registered, _)| registered == shortcut)
+        .map(|(_, action)| *action);
+
+    match action {
+        Some(GlobalAction::ToggleWindow) => toggle_window(app),
+        Some(GlobalAction::ToggleMute) => {
+            let muted = !state.muted.fetch_xor(true, Ordering::SeqCst);
+            let _ = app.emit("toggle-mute", muted);
+        }
+        None => {}
+    }
+}
+
+/// Hides the active window when it's in front, otherwise brings it back.
+/// During a call it stays, like with `hide_window`, and the call comes to the
+/// front instead.
+fn toggle_window<R: Runtime>(app: &AppHandle<R>) {
+    let Some(webview) = accounts::active_webview(app) else {
+        return;
+    };
+    let window = webview.window();
+
+    let in_front = window.is_visible().unwrap_or(false)
+        && window.is_focused().unwrap_or(false)
+        && !window.is_minimized().unwrap_or(false);
+
+    if in_front && calls::is_call_active(app) {
+        log::info!("Not hiding the window during a call");
+        calls::focus_call(app);
+    } else if in_front {
+        let _ = window.hide();
+    } else {
+        accounts::focus_active(app);
+    }
+}
+
+// =========================================================================
+//                               COMMANDS
+// =========================================================================
+
+/// Whether Messenger's sounds are muted, for pages loaded after the toggle.
+#[tauri::command]
+pub fn is_muted(state: tauri::State<'_, GlobalShortcuts>) -> bool {
+    state.muted.load(Ordering::SeqCst)
+}
+
+/// Hides the window, unless a call is going on and it would be hard to find.
+#[tauri::command]
+pub fn hide_window<R: Runtime>(webview: Webview<R>) {
+    if calls::is_call_active(webview.app_handle()) {
+        log::info!("Not hiding the window during a call");
+        return;
+    }
+    let _ = webview.window().hide();
+}
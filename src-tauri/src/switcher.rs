@@ -0,0 +1,228 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, Runtime, Webview};
+
+use crate::accounts;
+
+const HISTORY_FILE: &str = "switcher-history.json";
+
+/// Uses of an entry count half as much after this many days.
+const HALF_LIFE_DAYS: f64 = 14.0;
+
+/// Entries kept per account, the lowest ranked ones are forgotten first.
+const MAX_ENTRIES: usize = 200;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Usage {
+    label: String,
+    count: u32,
+    /// Unix timestamp in seconds.
+    last_used: u64,
+}
+
+impl Usage {
+    /// Frequency weighted by recency.
+    fn score(&self, now: u64) -> f64 {
+        let age_days = now.saturating_sub(self.last_used) as f64 / 86_400.0;
+        self.count as f64 * 0.5_f64.powf(age_days / HALF_LIFE_DAYS)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RankedEntry {
+    /// Thread path like `/t/<id>`, or `command:<name>` for app commands.
+    pub key: String,
+    pub label: String,
+    pub score: f64,
+}
+
+/// What was picked in the quick switcher, per account.
+pub struct SwitcherHistory {
+    accounts: Mutex<HashMap<String, HashMap<String, Usage>>>,
+    path: PathBuf,
+}
+
+impl SwitcherHistory {
+    pub fn load<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<Self> {
+        let path = app.path().app_local_data_dir()?.join(HISTORY_FILE);
+
+        let accounts = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+
+        Ok(Self {
+            accounts: Mutex::new(accounts),
+            path,
+        })
+    }
+
+    fn save(&self, accounts: &HashMap<String, HashMap<String, Usage>>) {
+        if let Some(dir) = self.path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        match serde_json::to_string(accounts) {
+            Ok(raw) => {
+                if let Err(e) = std::fs::write(&self.path, raw) {
+                    log::error!("Failed to save switcher history: {}", e);
+                }
+            }
+            Err(e) => log::error!("Failed to serialize switcher history: {}", e),
+        }
+    }
+}
+
+/// Entries of one account, best ranked first.
+fn rank(entries: &HashMap<String, Usage>, now: u64) -> Vec<RankedEntry> {
+    let mut ranking: Vec<RankedEntry> = entries
+        .iter()
+        .map(|(key, usage)| RankedEntry {
+            key: key.clone(),
+            label: usage.label.clone(),
+            score: usage.score(now),
+        })
+        .collect();
+    ranking.sort_by(|a, b| b.score.total_cmp(&a.score));
+    ranking
+}
+
+/// Counts a pick of `key` in the entries of one account, forgetting the
+/// lowest ranked entries past `MAX_ENTRIES`.
+fn record(entries: &mut HashMap<String, Usage>, key: String, label: String, now: u64) {
+    let usage = entries.entry(key).or_insert(Usage {
+        label: String::new(),
+        count: 0,
+        last_used: now,
+    });
+    usage.label = label;
+    usage.count += 1;
+    usage.last_used = now;
+
+    if entries.len() > MAX_ENTRIES {
+        let forgotten: Vec<String> = rank(entries, now)
+            .into_iter()
+            .skip(MAX_ENTRIES)
+            .map(|entry| entry.key)
+            .collect();
+        for key in forgotten {
+            entries.remove(&key);
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+// =========================================================================
+//                               COMMANDS
+// =========================================================================
+
+/// Entries picked before in the account of `webview`, best ranked first.
+#[tauri::command]
+pub fn switcher_ranking<R: Runtime>(
+    webview: Webview<R>,
+    history: tauri::State<'_, SwitcherHistory>,
+) -> Vec<RankedEntry> {
+    let Some(account_id) = accounts::account_id_for_label(webview.label()) else {
+        return Vec::new();
+    };
+
+    history
+        .accounts
+        .lock()
+        .unwrap()
+        .get(&account_id)
+        .map(|entries| rank(entries, now()))
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn record_switcher_pick<R: Runtime>(
+    webview: Webview<R>,
+    history: tauri::State<'_, SwitcherHistory>,
+    key: String,
+    label: String,
+) {
+    let Some(account_id) = accounts::account_id_for_label(webview.label()) else {
+        return;
+    };
+
+    let mut accounts = history.accounts.lock().unwrap();
+    record(accounts.entry(account_id).or_default(), key, label, now());
+    history.save(&accounts);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 86_400;
+    const NOW: u64 = 1_000 * DAY;
+
+    fn usage(count: u32, last_used: u64) -> Usage {
+        Usage {
+            label: String::new(),
+            count,
+            last_used,
+        }
+    }
+
+    #[test]
+    fn score_halves_every_half_life() {
+        let half_life = HALF_LIFE_DAYS as u64 * DAY;
+        assert_eq!(usage(8, NOW).score(NOW), 8.0);
+        assert!((usage(8, NOW - half_life).score(NOW) - 4.0).abs() < 1e-9);
+        assert!((usage(8, NOW - 2 * half_life).score(NOW) - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn score_of_future_picks_is_not_boosted() {
+        assert_eq!(usage(3, NOW + DAY).score(NOW), 3.0);
+    }
+
+    #[test]
+    fn rank_puts_recent_picks_above_old_frequent_ones() {
+        let entries = HashMap::from([
+            ("/t/old".to_string(), usage(10, NOW - 100 * DAY)),
+            ("/t/recent".to_string(), usage(2, NOW)),
+            ("/t/middle".to_string(), usage(1, NOW - DAY)),
+        ]);
+        let keys: Vec<String> = rank(&entries, NOW)
+            .into_iter()
+            .map(|entry| entry.key)
+            .collect();
+        assert_eq!(keys, ["/t/recent", "/t/middle", "/t/old"]);
+    }
+
+    #[test]
+    fn record_counts_picks_and_keeps_the_latest_label() {
+        let mut entries = HashMap::new();
+        record(&mut entries, "/t/1".into(), "Alice".into(), NOW - DAY);
+        record(&mut entries, "/t/1".into(), "Alice B".into(), NOW);
+
+        let usage = &entries["/t/1"];
+        assert_eq!(usage.count, 2);
+        assert_eq!(usage.label, "Alice B");
+        assert_eq!(usage.last_used, NOW);
+    }
+
+    #[test]
+    fn record_forgets_the_lowest_ranked_entries() {
+        let mut entries: HashMap<String, Usage> = (0..MAX_ENTRIES as u64)
+            .map(|i| (format!("/t/{}", i), usage(1, NOW - (i + 1) * DAY)))
+            .collect();
+        record(&mut entries, "/t/new".into(), "New".into(), NOW);
+
+        assert_eq!(entries.len(), MAX_ENTRIES);
+        assert!(entries.contains_key("/t/new"));
+        assert!(entries.contains_key("/t/0"));
+        assert!(!entries.contains_key(&format!("/t/{}", MAX_ENTRIES - 1)));
+    }
+}
@@ -0,0 +1,39 @@
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_updater::UpdaterExt;
+
+use crate::calls;
+
+/// Checks for an update in the background, installing it and restarting the
+/// app when one is found. The restart waits for a call to end.
+pub fn spawn_update_check<R: Runtime>(app_handle: AppHandle<R>) {
+    tauri::async_runtime::spawn(async move {
+        match app_handle.updater() {
+            Ok(updater) => {
+                match updater.check().await {
+                    Ok(Some(update)) => {
+                        log::info!("Update found: {}", update.version);
+                        // Download and install automatically
+                        if let Err(e) = update.download_and_install(|_, _| {}, || {}).await {
+                            log::error!("Update failed: {}", e);
+                        } else {
+                            log::info!("Update installed! Restarting...");
+                            calls::restart_when_idle(&app_handle);
+                        }
+                    }
+                    Ok(None) => log::info!("You are on the latest version."),
+                    Err(e) => log::error!("Failed to check for updates: {}", e),
+                }
+            }
+            Err(e) => log::error!("Failed to initialize updater: {}", e),
+        }
+    });
+}
+
+// =========================================================================
+//                               COMMANDS
+// =========================================================================
+
+#[tauri::command]
+pub fn check_for_updates<R: Runtime>(app: AppHandle<R>) {
+    spawn_update_check(app);
+}
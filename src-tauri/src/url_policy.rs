@@ -0,0 +1,338 @@
+use tauri::Url;
+
+/// Custom URL schemes Messterial registers with the OS.
+pub const DEEP_LINK_SCHEMES: [&str; 2] = ["messterial", "messenger"];
+
+/// Root of a page served by one of the app's own URI schemes. WebView2 only
+/// loads them over `http://<scheme>.localhost`.
+pub fn app_page_url(scheme: &str) -> Url {
+    let url = if cfg!(windows) {
+        format!("http://{}.localhost/", scheme)
+    } else {
+        format!("{}://localhost/", scheme)
+    };
+    Url::parse(&url).expect("app page URLs are valid")
+}
+
+fn host_matches(url: &Url, domain: &str) -> bool {
+    match url.host_str() {
+        Some(host) => host == domain || host.ends_with(&format!(".{}", domain)),
+        None => false,
+    }
+}
+
+/// Whether `path` is `prefix` or below it, so `/call` doesn't match `/callback`.
+fn path_under(path: &str, prefix: &str) -> bool {
+    path.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// `https://messenger.com` or one of its subdomains.
+pub fn is_messenger(url: &Url) -> bool {
+    url.scheme() == "https" && host_matches(url, "messenger.com")
+}
+
+/// Whether a `window.open` request may open inside the app (calls, auth,
+/// media) instead of being denied.
+pub fn allows_popup(url: &Url) -> bool {
+    // Calls often start with about:blank
+    if url.as_str().starts_with("about:blank") {
+        return true;
+    }
+
+    url.scheme() == "https"
+        && (host_matches(url, "messenger.com")
+            || host_matches(url, "facebook.com")
+            || host_matches(url, "fbcdn.net"))
+}
+
+/// Messenger paths of call windows.
+const CALL_PATHS: [&str; 3] = ["/groupcall", "/videocall", "/call"];
+
+/// Facebook paths of login and verification popups.
+const AUTH_PATHS: [&str; 4] = [
+    "/login",
+    "/checkpoint",
+    "/dialog/oauth",
+    "/two_step_verification",
+];
+
+/// What a popup window is for, so each kind remembers its own size and position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PopupKind {
+    Call,
+    Auth,
+    MediaViewer,
+    Other,
+}
+
+impl PopupKind {
+    /// Window label of the kind. It stays the same across launches, so the
+    /// window-state plugin can restore the window.
+    pub fn label(self) -> &'static str {
+        match self {
+            PopupKind::Call => "call",
+            PopupKind::Auth => "auth",
+            PopupKind::MediaViewer => "media-viewer",
+            PopupKind::Other => "popup",
+        }
+    }
+}
+
+/// A photo or video on Facebook's CDN, which the media viewer can show.
+pub fn is_media_file(url: &Url) -> bool {
+    url.scheme() == "https" && host_matches(url, "fbcdn.net")
+}
+
+/// Guesses what an allowed popup is for from its URL.
+pub fn popup_kind(url: &Url) -> PopupKind {
+    // Calls often start with about:blank
+    if url.as_str().starts_with("about:blank") {
+        return PopupKind::Call;
+    }
+
+    let path = url.path();
+    if host_matches(url, "messenger.com") {
+        if CALL_PATHS.iter().any(|prefix| path_under(path, prefix)) {
+            return PopupKind::Call;
+        }
+        if path.starts_with("/messenger_media") {
+            return PopupKind::MediaViewer;
+        }
+    }
+
+    if host_matches(url, "fbcdn.net") {
+        return PopupKind::MediaViewer;
+    }
+
+    if host_matches(url, "facebook.com") && AUTH_PATHS.iter().any(|prefix| path_under(path, prefix))
+    {
+        return PopupKind::Auth;
+    }
+
+    PopupKind::Other
+}
+
+fn is_valid_thread_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+}
+
+/// Messenger URL of a conversation from its path, `t/<thread-id>` or
+/// `e2ee/t/<thread-id>`, with or without slashes around it.
+pub fn conversation_url(path: &str) -> Option<Url> {
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+    let target = match segments.as_slice() {
+        ["t", id] if is_valid_thread_id(id) => format!("/t/{}", id),
+        ["e2ee", "t", id] if is_valid_thread_id(id) => format!("/e2ee/t/{}", id),
+        _ => return None,
+    };
+
+    Url::parse(&format!("https://www.messenger.com{}", target)).ok()
+}
+
+/// Messenger's home page, or the conversation at `path`, see [`conversation_url`].
+fn home_or_conversation_url(path: &str) -> Option<Url> {
+    if path.trim_matches('/').is_empty() {
+        return Url::parse("https://www.messenger.com/").ok();
+    }
+    conversation_url(path)
+}
+
+/// Turns a link handed to the app by the OS into the Messenger URL the main
+/// webview should open. Accepts:
+///
+/// - `messterial://t/<thread-id>` and `messenger://t/<thread-id>`
+///   (also `e2ee/t/<thread-id>`, or no path at all to just open the app)
+/// - `https://www.messenger.com/` and its conversations, `t/<thread-id>` or
+///   `e2ee/t/<thread-id>`, so outside links can't reach `/logout` or settings
+/// - `https://www.facebook.com/messages/t/<thread-id>`
+///
+/// Anything else is rejected.
+pub fn resolve_link(link: &str) -> Option<Url> {
+    let url = Url::parse(link).ok()?;
+
+    if DEEP_LINK_SCHEMES.contains(&url.scheme()) {
+        // `messterial://t/123` parses with `t` as the host
+        let path = format!("{}{}", url.host_str().unwrap_or_default(), url.path());
+        return home_or_conversation_url(&path);
+    }
+
+    if is_messenger(&url) {
+        return home_or_conversation_url(url.path());
+    }
+
+    if url.scheme() == "https" && host_matches(&url, "facebook.com") {
+        let id = url
+            .path()
+            .strip_prefix("/messages/t/")?
+            .trim_end_matches('/');
+        if is_valid_thread_id(id) {
+            return Url::parse(&format!("https://www.messenger.com/t/{}", id)).ok();
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(link: &str) -> Option<String> {
+        resolve_link(link).map(String::from)
+    }
+
+    fn kind(url: &str) -> PopupKind {
+        popup_kind(&url.parse().unwrap())
+    }
+
+    #[test]
+    fn resolve_link_opens_deep_links() {
+        assert_eq!(
+            resolve("messterial://t/123").as_deref(),
+            Some("https://www.messenger.com/t/123")
+        );
+        assert_eq!(
+            resolve("messenger://e2ee/t/456/").as_deref(),
+            Some("https://www.messenger.com/e2ee/t/456")
+        );
+        assert_eq!(
+            resolve("messterial://t/123?ref=x").as_deref(),
+            Some("https://www.messenger.com/t/123")
+        );
+        assert_eq!(
+            resolve("messterial://").as_deref(),
+            Some("https://www.messenger.com/")
+        );
+    }
+
+    #[test]
+    fn resolve_link_rejects_other_deep_links() {
+        assert_eq!(resolve("messterial://settings"), None);
+        assert_eq!(resolve("messterial://t/a%20b"), None);
+        assert_eq!(resolve("messterial://t/1/2"), None);
+    }
+
+    #[test]
+    fn resolve_link_opens_messenger_threads() {
+        assert_eq!(
+            resolve("https://www.messenger.com/t/1?x=y").as_deref(),
+            Some("https://www.messenger.com/t/1")
+        );
+        assert_eq!(
+            resolve("https://messenger.com/e2ee/t/2/").as_deref(),
+            Some("https://www.messenger.com/e2ee/t/2")
+        );
+        assert_eq!(
+            resolve("https://messenger.com/").as_deref(),
+            Some("https://www.messenger.com/")
+        );
+    }
+
+    #[test]
+    fn resolve_link_rejects_other_messenger_pages() {
+        for link in [
+            "https://www.messenger.com/logout",
+            "https://www.messenger.com/settings",
+            "https://www.messenger.com/t/1/settings",
+            "https://www.messenger.com/login/?next=/t/1",
+            "https://m.messenger.com/marketplace/",
+        ] {
+            assert_eq!(resolve(link), None, "{}", link);
+        }
+    }
+
+    #[test]
+    fn resolve_link_maps_facebook_threads() {
+        assert_eq!(
+            resolve("https://www.facebook.com/messages/t/42/").as_deref(),
+            Some("https://www.messenger.com/t/42")
+        );
+        assert_eq!(resolve("https://www.facebook.com/profile.php?id=1"), None);
+        assert_eq!(resolve("https://www.facebook.com/messages/t/a%2Fb"), None);
+    }
+
+    #[test]
+    fn resolve_link_rejects_everything_else() {
+        for link in [
+            "http://www.messenger.com/t/1",
+            "https://evilmessenger.com/t/1",
+            "https://messenger.com.evil.example/t/1",
+            "https://example.com/",
+            "file:///etc/passwd",
+            "javascript:alert(1)",
+            "not a link",
+        ] {
+            assert_eq!(resolve(link), None, "{}", link);
+        }
+    }
+
+    #[test]
+    fn conversation_url_accepts_thread_paths() {
+        let url = |path| conversation_url(path).map(String::from);
+        assert_eq!(
+            url("t/123").as_deref(),
+            Some("https://www.messenger.com/t/123")
+        );
+        assert_eq!(
+            url("/e2ee/t/1.2_3-4/").as_deref(),
+            Some("https://www.messenger.com/e2ee/t/1.2_3-4")
+        );
+        for path in ["", "t", "t/", "x/1", "t/1/2", "e2ee/1", "t/a?b", "t/..%2f"] {
+            assert_eq!(url(path), None, "{}", path);
+        }
+    }
+
+    #[test]
+    fn popup_kind_recognizes_calls() {
+        assert_eq!(kind("about:blank"), PopupKind::Call);
+        assert_eq!(
+            kind("https://www.messenger.com/groupcall/ROOM:123/"),
+            PopupKind::Call
+        );
+        assert_eq!(
+            kind("https://www.messenger.com/videocall/incoming/?x=1"),
+            PopupKind::Call
+        );
+        assert_eq!(kind("https://www.messenger.com/call"), PopupKind::Call);
+        assert_eq!(kind("https://www.facebook.com/call/"), PopupKind::Other);
+    }
+
+    #[test]
+    fn popup_kind_matches_whole_call_segments() {
+        for url in [
+            "https://www.messenger.com/callback",
+            "https://www.messenger.com/calls_history/",
+            "https://www.messenger.com/videocalls",
+            "https://www.messenger.com/groupcall_settings",
+        ] {
+            assert_eq!(kind(url), PopupKind::Other, "{}", url);
+        }
+    }
+
+    #[test]
+    fn popup_kind_recognizes_media_and_auth() {
+        assert_eq!(
+            kind("https://scontent.xx.fbcdn.net/v/t1/photo.jpg"),
+            PopupKind::MediaViewer
+        );
+        assert_eq!(
+            kind("https://www.messenger.com/messenger_media/?attachment_id=1"),
+            PopupKind::MediaViewer
+        );
+        assert_eq!(
+            kind("https://www.facebook.com/login/device-based/"),
+            PopupKind::Auth
+        );
+        assert_eq!(
+            kind("https://www.facebook.com/dialog/oauth?client_id=1"),
+            PopupKind::Auth
+        );
+        assert_eq!(kind("https://www.messenger.com/login"), PopupKind::Other);
+        assert_eq!(kind("https://www.messenger.com/t/1"), PopupKind::Other);
+    }
+}
@@ -0,0 +1,426 @@
+use notify::RecommendedWatcher;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::webview::{PageLoadEvent, PageLoadPayload};
+use tauri::{AppHandle, Manager, Runtime, Webview};
+
+use crate::{accounts, url_policy, watcher};
+
+const USER_CSS_FILE: &str = "user.css";
+const USER_JS_FILE: &str = "user.js";
+const USERSCRIPTS_DIR: &str = "userscripts";
+
+/// Defines `__messterialMatches(patterns)` for the userscripts that are part
+/// of the initialization script, where the page URL is only known at runtime.
+pub const MATCHER_JS: &str = r#"
+const __messterialMatches = (patterns) => patterns.length === 0 || patterns.some((pattern) => {
+    const parts = pattern.split('*').map((part) => part.replace(/[.+?^${}()|[\]\\]/g, '\\$&'));
+    return new RegExp('^' + parts.join('.*') + '$').test(location.href);
+});
+"#;
+
+/// When a userscript runs, from its `@run-at` header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RunAt {
+    /// Before Messenger's own scripts.
+    Start,
+    /// Once the DOM is parsed, right after Messterial's scripts.
+    End,
+    /// Once the page finished loading.
+    #[default]
+    Idle,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserScript {
+    pub name: String,
+    /// `@match`/`@include` patterns where `*` matches anything. Empty means every page.
+    pub matches: Vec<String>,
+    pub run_at: RunAt,
+    pub source: String,
+}
+
+impl UserScript {
+    /// Reads the `// ==UserScript==` metadata block, if there is one.
+    fn parse(name: String, source: String) -> Self {
+        let mut script = Self {
+            name,
+            matches: Vec::new(),
+            run_at: RunAt::default(),
+            source: String::new(),
+        };
+
+        let mut in_header = false;
+        for line in source.lines().map(str::trim) {
+            if line == "// ==UserScript==" {
+                in_header = true;
+                continue;
+            }
+            if line == "// ==/UserScript==" {
+                break;
+            }
+            if !in_header {
+                continue;
+            }
+
+            let Some(meta) = line
+                .strip_prefix("//")
+                .and_then(|line| line.trim_start().strip_prefix('@'))
+            else {
+                continue;
+            };
+            let (key, value) = match meta.split_once(char::is_whitespace) {
+                Some((key, value)) => (key, value.trim()),
+                None => (meta, ""),
+            };
+
+            match key {
+                "name" if !value.is_empty() => script.name = value.into(),
+                "match" | "include" if !value.is_empty() => script.matches.push(value.into()),
+                "run-at" => match value {
+                    "document-start" => script.run_at = RunAt::Start,
+                    "document-end" => script.run_at = RunAt::End,
+                    "document-idle" => script.run_at = RunAt::Idle,
+                    other => log::warn!("Unknown @run-at '{}' in {}", other, script.name),
+                },
+                _ => {}
+            }
+        }
+
+        script.source = source;
+        script
+    }
+
+    pub fn matches_url(&self, url: &str) -> bool {
+        self.matches.is_empty()
+            || self
+                .matches
+                .iter()
+                .any(|pattern| glob_matches(pattern, url))
+    }
+
+    /// The script in its own scope, so one failing doesn't stop the others.
+    fn wrapped(&self) -> String {
+        format!(
+            "(() => {{ try {{\n{}\n}} catch (e) {{ console.error('Messterial: userscript ' + {} + ' failed', e); }} }})();",
+            self.source,
+            serde_json::to_string(&self.name).unwrap_or_else(|_| "\"\"".into())
+        )
+    }
+}
+
+/// Matches `text` against a pattern where `*` stands for any run of characters.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = text.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.ends_with(last)
+}
+
+/// The user's own stylesheet and scripts from the app config dir.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserContent {
+    pub css: String,
+    pub scripts: Vec<UserScript>,
+}
+
+impl UserContent {
+    /// Reads `user.css`, `user.js` and every `.js` file in `userscripts/`.
+    fn read(dir: &Path) -> Self {
+        let css = std::fs::read_to_string(dir.join(USER_CSS_FILE)).unwrap_or_default();
+
+        let mut files = vec![dir.join(USER_JS_FILE)];
+        if let Ok(entries) = std::fs::read_dir(dir.join(USERSCRIPTS_DIR)) {
+            let mut userscripts: Vec<PathBuf> = entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "js"))
+                .collect();
+            userscripts.sort();
+            files.extend(userscripts);
+        }
+
+        let scripts = files
+            .into_iter()
+            .filter_map(|path| {
+                let source = std::fs::read_to_string(&path).ok()?;
+                let name = path.file_name()?.to_string_lossy().into_owned();
+                Some(UserScript::parse(name, source))
+            })
+            .collect();
+
+        Self { css, scripts }
+    }
+
+    fn scripts_at(&self, run_at: RunAt) -> impl Iterator<Item = &UserScript> {
+        self.scripts
+            .iter()
+            .filter(move |script| script.run_at == run_at)
+    }
+
+    /// Scripts for the initialization script, each guarded by its match patterns.
+    /// Needs [`MATCHER_JS`] in scope.
+    pub fn init_scripts(&self, run_at: RunAt) -> String {
+        self.scripts_at(run_at)
+            .map(|script| {
+                format!(
+                    "if (__messterialMatches({})) {}",
+                    serde_json::to_string(&script.matches).unwrap_or_else(|_| "[]".into()),
+                    script.wrapped()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Holds the user content and keeps it in sync with the files on disk.
+pub struct UserContentStore {
+    content: Mutex<UserContent>,
+    dir: PathBuf,
+    watcher: Mutex<Option<RecommendedWatcher>>,
+}
+
+impl UserContentStore {
+    pub fn load<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<Self> {
+        let dir = app.path().app_config_dir()?;
+        // So there is an obvious place to drop userscripts into
+        let _ = std::fs::create_dir_all(dir.join(USERSCRIPTS_DIR));
+
+        Ok(Self {
+            content: Mutex::new(UserContent::read(&dir)),
+            dir,
+            watcher: Mutex::new(None),
+        })
+    }
+
+    pub fn get(&self) -> UserContent {
+        self.content.lock().unwrap().clone()
+    }
+
+    fn is_user_file(&self, path: &Path) -> bool {
+        path == self.dir.join(USER_CSS_FILE)
+            || path == self.dir.join(USER_JS_FILE)
+            || path.parent() == Some(self.dir.join(USERSCRIPTS_DIR).as_path())
+    }
+}
+
+/// Current user content, or nothing before the store is loaded.
+pub fn current<R: Runtime>(app: &AppHandle<R>) -> UserContent {
+    app.try_state::<UserContentStore>()
+        .map(|store| store.get())
+        .unwrap_or_default()
+}
+
+fn style_script(css: &str) -> String {
+    format!(
+        "(() => {{
+            let style = document.getElementById('messterial-user-style');
+            if (!style) {{
+                style = document.createElement('style');
+                style.id = 'messterial-user-style';
+                document.head.append(style);
+            }}
+            style.textContent = {};
+        }})();",
+        serde_json::to_string(css).unwrap_or_else(|_| "\"\"".into())
+    )
+}
+
+/// Page load hook of the Messenger webviews. Runs the `document-idle` scripts
+/// from disk, so they pick up edits whenever the page is reloaded.
+pub fn on_page_load<R: Runtime>(webview: &Webview<R>, payload: &PageLoadPayload<'_>) {
+    if payload.event() != PageLoadEvent::Finished || !url_policy::is_messenger(payload.url()) {
+        return;
+    }
+
+    let content = current(webview.app_handle());
+    // The stylesheet from the initialization script may be older
+    let _ = webview.eval(style_script(&content.css));
+
+    for script in content.scripts_at(RunAt::Idle) {
+        if script.matches_url(payload.url().as_str()) {
+            let _ = webview.eval(script.wrapped());
+        }
+    }
+}
+
+fn reload<R: Runtime>(app: &AppHandle<R>) {
+    let store = app.state::<UserContentStore>();
+    let content = UserContent::read(&store.dir);
+    let previous = std::mem::replace(&mut *store.content.lock().unwrap(), content.clone());
+
+    if content.css != previous.css {
+        let script = style_script(&content.css);
+        for webview in accounts::account_webviews(app) {
+            let _ = webview.eval(&script);
+        }
+    }
+
+    let changed = |run_at| content.scripts_at(run_at).ne(previous.scripts_at(run_at));
+
+    if changed(RunAt::Start) || changed(RunAt::End) {
+        log::info!(
+            "Userscripts running at document-start or document-end changed, restart to apply"
+        );
+    }
+
+    if changed(RunAt::Idle) {
+        log::info!("Userscripts changed, reloading Messenger");
+        for webview in accounts::account_webviews(app) {
+            let _ = webview.reload();
+        }
+    }
+}
+
+/// Starts watching the user content files, applying changes as they are saved.
+pub fn watch<R: Runtime>(app: &AppHandle<R>) {
+    let store = app.state::<UserContentStore>();
+    let dirs = [store.dir.clone(), store.dir.join(USERSCRIPTS_DIR)];
+
+    let app_handle = app.clone();
+    let result = watcher::watch(&dirs, move |paths| {
+        let store = app_handle.state::<UserContentStore>();
+        if paths.iter().any(|path| store.is_user_file(path)) {
+            reload(&app_handle);
+        }
+    });
+
+    match result {
+        Ok(watcher) => *store.watcher.lock().unwrap() = Some(watcher),
+        Err(e) => log::warn!("Failed to watch user content: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_without_wildcards_matches_exactly() {
+        assert!(glob_matches(
+            "https://www.messenger.com/",
+            "https://www.messenger.com/"
+        ));
+        assert!(!glob_matches(
+            "https://www.messenger.com/",
+            "https://www.messenger.com/t/1"
+        ));
+        assert!(glob_matches("", ""));
+        assert!(!glob_matches("", "x"));
+    }
+
+    #[test]
+    fn glob_star_matches_any_run() {
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("*", "anything"));
+        assert!(glob_matches(
+            "https://www.messenger.com/*",
+            "https://www.messenger.com/t/123"
+        ));
+        assert!(glob_matches(
+            "https://*.messenger.com/*",
+            "https://www.messenger.com/e2ee/t/1"
+        ));
+        assert!(!glob_matches(
+            "https://*.messenger.com/*",
+            "https://www.facebook.com/"
+        ));
+    }
+
+    #[test]
+    fn glob_anchors_both_ends() {
+        assert!(!glob_matches("*/t/*", "https://www.messenger.com/"));
+        assert!(glob_matches("*.com", "messenger.com"));
+        assert!(!glob_matches("*.com", "messenger.com.evil"));
+        assert!(!glob_matches("messenger*", "www.messenger.com"));
+    }
+
+    #[test]
+    fn glob_parts_do_not_overlap() {
+        assert!(!glob_matches("a*a", "a"));
+        assert!(glob_matches("a*a", "aa"));
+        assert!(!glob_matches("ab*bc", "abc"));
+        assert!(glob_matches("*ab*ab", "xabyab"));
+        assert!(!glob_matches("*ab*ab", "xab"));
+    }
+
+    fn parse(source: &str) -> UserScript {
+        UserScript::parse("file.js".into(), source.into())
+    }
+
+    #[test]
+    fn parse_reads_the_header() {
+        let source = "// ==UserScript==
+// @name        Dark bubbles
+// @match       https://www.messenger.com/*
+// @include     https://messenger.com/*
+// @description ignored
+// ==/UserScript==
+document.body.dataset.x = 1;";
+        let script = parse(source);
+
+        assert_eq!(script.name, "Dark bubbles");
+        assert_eq!(
+            script.matches,
+            ["https://www.messenger.com/*", "https://messenger.com/*"]
+        );
+        assert_eq!(script.run_at, RunAt::Idle);
+        assert_eq!(script.source, source);
+    }
+
+    #[test]
+    fn parse_reads_run_at() {
+        let run_at = |value: &str| {
+            parse(&format!(
+                "// ==UserScript==\n// @run-at {}\n// ==/UserScript==",
+                value
+            ))
+            .run_at
+        };
+        assert_eq!(run_at("document-start"), RunAt::Start);
+        assert_eq!(run_at("document-end"), RunAt::End);
+        assert_eq!(run_at("document-idle"), RunAt::Idle);
+        assert_eq!(run_at("context-menu"), RunAt::Idle);
+    }
+
+    #[test]
+    fn parse_without_a_header_keeps_the_defaults() {
+        let script = parse("// @name Not a header\n// @run-at document-start\nrun();");
+        assert_eq!(script.name, "file.js");
+        assert!(script.matches.is_empty());
+        assert_eq!(script.run_at, RunAt::Idle);
+    }
+
+    #[test]
+    fn parse_stops_at_the_end_of_the_header() {
+        let script = parse(
+            "// ==UserScript==\n// @name Early\n// ==/UserScript==\n// @name Late\n// @match https://example.com/*",
+        );
+        assert_eq!(script.name, "Early");
+        assert!(script.matches.is_empty());
+    }
+
+    #[test]
+    fn parse_accepts_keys_without_a_space() {
+        let script = parse(
+            "// ==UserScript==\n//@name Tight\n//@match https://www.messenger.com/t/*\n//@run-at document-end\n// ==/UserScript==",
+        );
+        assert_eq!(script.name, "Tight");
+        assert_eq!(script.matches, ["https://www.messenger.com/t/*"]);
+        assert_eq!(script.run_at, RunAt::End);
+    }
+}
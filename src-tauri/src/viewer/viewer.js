@@ -0,0 +1,201 @@
+// Media viewer, see media_viewer.rs
+const invoke = window.__TAURI__.core.invoke;
+
+const MIN_SCALE = 0.1;
+const MAX_SCALE = 8;
+const ZOOM_STEP = 1.25;
+
+const stage = document.getElementById('viewer-stage');
+const image = document.getElementById('viewer-image');
+const video = document.getElementById('viewer-video');
+const counter = document.getElementById('viewer-counter');
+const previous = document.getElementById('viewer-previous');
+const next = document.getElementById('viewer-next');
+const zoomReset = document.getElementById('viewer-zoom-reset');
+const copy = document.getElementById('viewer-copy');
+const toast = document.getElementById('viewer-toast');
+
+let items = [];
+let index = 0;
+
+// The image is scaled and moved with a transform, `fit` is the scale at which it fills the window
+let scale = 1;
+let fit = 1;
+let x = 0;
+let y = 0;
+
+const current = () => items[index];
+const isImage = () => current()?.kind === 'image';
+
+const showToast = (text) => {
+    toast.textContent = text;
+    toast.classList.add('visible');
+    clearTimeout(showToast.timer);
+    showToast.timer = setTimeout(() => toast.classList.remove('visible'), 2000);
+};
+
+const apply = () => {
+    image.style.transform = `translate(${x}px, ${y}px) scale(${scale})`;
+    zoomReset.textContent = `${Math.round(scale * 100)}%`;
+    stage.classList.toggle('zoomed', scale > fit);
+};
+
+// Keeps the image on screen: centered when it's smaller than the window
+const clamp = () => {
+    const width = image.naturalWidth * scale;
+    const height = image.naturalHeight * scale;
+    const clampAxis = (offset, size, room) =>
+        size <= room ? (room - size) / 2 : Math.min(0, Math.max(room - size, offset));
+    x = clampAxis(x, width, stage.clientWidth);
+    y = clampAxis(y, height, stage.clientHeight);
+};
+
+const fitToWindow = () => {
+    if (!image.naturalWidth) return;
+    fit = Math.min(1, stage.clientWidth / image.naturalWidth, stage.clientHeight / image.naturalHeight);
+    scale = fit;
+    clamp();
+    apply();
+};
+
+// Zooms by `factor` around a point of the window, the center by default
+const zoom = (factor, pointX = stage.clientWidth / 2, pointY = stage.clientHeight / 2) => {
+    if (!isImage() || !image.naturalWidth) return;
+    const target = Math.min(MAX_SCALE, Math.max(Math.min(MIN_SCALE, fit), scale * factor));
+    x = pointX - ((pointX - x) * target) / scale;
+    y = pointY - ((pointY - y) * target) / scale;
+    scale = target;
+    clamp();
+    apply();
+};
+
+const show = () => {
+    const item = current();
+    if (!item) return;
+
+    counter.textContent = `${index + 1} / ${items.length}`;
+    previous.hidden = index === 0;
+    next.hidden = index === items.length - 1;
+    copy.disabled = item.kind !== 'image';
+    ['viewer-zoom-out', 'viewer-zoom-reset', 'viewer-zoom-in'].forEach((id) => {
+        document.getElementById(id).disabled = item.kind !== 'image';
+    });
+
+    if (item.kind === 'image') {
+        video.pause();
+        video.hidden = true;
+        video.removeAttribute('src');
+        image.hidden = false;
+        image.src = item.url;
+    } else {
+        image.hidden = true;
+        image.removeAttribute('src');
+        video.hidden = false;
+        video.src = item.url;
+        zoomReset.textContent = '';
+    }
+};
+
+const go = (offset) => {
+    const target = Math.min(items.length - 1, Math.max(0, index + offset));
+    if (target === index) return;
+    index = target;
+    show();
+};
+
+const save = async () => {
+    const item = current();
+    if (!item) return;
+    try {
+        const path = await invoke('save_media', { url: item.url });
+        if (path) showToast(`Saved to ${path}`);
+    } catch (err) {
+        showToast(`Could not save: ${err}`);
+    }
+};
+
+const copyImage = async () => {
+    if (!isImage()) return;
+    try {
+        await invoke('copy_media', { url: current().url });
+        showToast('Copied to the clipboard');
+    } catch (err) {
+        showToast(`Could not copy: ${err}`);
+    }
+};
+
+image.addEventListener('load', fitToWindow);
+image.addEventListener('error', () => showToast('This photo is no longer available'));
+window.addEventListener('resize', fitToWindow);
+
+// Scrolling zooms around the pointer
+stage.addEventListener('wheel', (e) => {
+    e.preventDefault();
+    zoom(e.deltaY < 0 ? ZOOM_STEP : 1 / ZOOM_STEP, e.clientX, e.clientY);
+}, { passive: false });
+
+// Double-click switches between fitting the window and the actual size
+stage.addEventListener('dblclick', (e) => {
+    if (!isImage()) return;
+    if (scale > fit) fitToWindow();
+    else zoom(1 / scale, e.clientX, e.clientY);
+});
+
+// Dragging pans a zoomed image
+stage.addEventListener('pointerdown', (e) => {
+    if (!isImage() || scale <= fit || e.button !== 0) return;
+    const start = { pointerX: e.clientX, pointerY: e.clientY, x, y };
+    stage.setPointerCapture(e.pointerId);
+    stage.classList.add('panning');
+
+    const move = (event) => {
+        x = start.x + event.clientX - start.pointerX;
+        y = start.y + event.clientY - start.pointerY;
+        clamp();
+        apply();
+    };
+    const end = () => {
+        stage.classList.remove('panning');
+        stage.removeEventListener('pointermove', move);
+        stage.removeEventListener('pointerup', end);
+        stage.removeEventListener('pointercancel', end);
+    };
+    stage.addEventListener('pointermove', move);
+    stage.addEventListener('pointerup', end);
+    stage.addEventListener('pointercancel', end);
+});
+
+document.addEventListener('keydown', (e) => {
+    const modifier = e.ctrlKey || e.metaKey;
+    if (modifier && e.key.toLowerCase() === 's') save();
+    else if (modifier && e.key.toLowerCase() === 'c') copyImage();
+    else if (modifier) return;
+    else if (e.key === 'ArrowLeft') go(-1);
+    else if (e.key === 'ArrowRight') go(1);
+    else if (e.key === 'Home') go(-index);
+    else if (e.key === 'End') go(items.length);
+    else if (e.key === '+' || e.key === '=') zoom(ZOOM_STEP);
+    else if (e.key === '-') zoom(1 / ZOOM_STEP);
+    else if (e.key === '0') fitToWindow();
+    else if (e.key === 'Escape') window.__TAURI__.window.getCurrentWindow().close();
+    else return;
+    e.preventDefault();
+});
+
+previous.addEventListener('click', () => go(-1));
+next.addEventListener('click', () => go(1));
+document.getElementById('viewer-zoom-in').addEventListener('click', () => zoom(ZOOM_STEP));
+document.getElementById('viewer-zoom-out').addEventListener('click', () => zoom(1 / ZOOM_STEP));
+zoomReset.addEventListener('click', fitToWindow);
+copy.addEventListener('click', copyImage);
+document.getElementById('viewer-save').addEventListener('click', save);
+
+const load = (gallery) => {
+    items = gallery.items;
+    index = gallery.index;
+    show();
+    stage.focus();
+};
+
+window.__TAURI__.event.listen('media-viewer-gallery', (event) => load(event.payload));
+invoke('media_viewer_gallery').then(load);
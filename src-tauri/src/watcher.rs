@@ -0,0 +1,43 @@
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Editors often save a file in several steps, so changes this close together
+/// are handled once.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Watches the files directly inside each of `dirs` and calls `on_change`
+/// with the changed paths. Watching stops once the returned watcher is dropped.
+pub fn watch<F>(dirs: &[PathBuf], on_change: F) -> notify::Result<RecommendedWatcher>
+where
+    F: Fn(Vec<PathBuf>) + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel::<notify::Event>();
+
+    let mut watcher =
+        notify::recommended_watcher(move |result: notify::Result<notify::Event>| match result {
+            Ok(event) if !event.kind.is_access() => {
+                let _ = tx.send(event);
+            }
+            Ok(_) => {}
+            Err(e) => log::warn!("File watcher error: {}", e),
+        })?;
+    for dir in dirs {
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    }
+
+    std::thread::spawn(move || {
+        while let Ok(event) = rx.recv() {
+            let mut paths = event.paths;
+            while let Ok(event) = rx.recv_timeout(DEBOUNCE) {
+                paths.extend(event.paths);
+            }
+            paths.sort();
+            paths.dedup();
+            on_change(paths);
+        }
+    });
+
+    Ok(watcher)
+}
@@ -0,0 +1,502 @@
+use serde::{Deserialize, Serialize};
+use tauri::menu::{IsMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+use tauri::{AppHandle, Manager, Runtime, Window};
+
+/// Prefix of the ids of the window menu items, followed by the action and the
+/// window label.
+const MENU_ID_PREFIX: &str = "window-menu";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowButton {
+    Minimize,
+    Maximize,
+    Close,
+}
+
+/// Where the custom titlebar draws its window buttons.
+#[derive(Debug, Clone, Serialize)]
+pub struct ControlsLayout {
+    pub left: Vec<WindowButton>,
+    pub right: Vec<WindowButton>,
+    /// The OS draws the buttons over the titlebar (macOS traffic lights).
+    pub native: bool,
+    /// The maximize button should open the Windows 11 snap layouts, see
+    /// `place_maximize_button`.
+    pub snap_layouts: bool,
+}
+
+impl Default for ControlsLayout {
+    fn default() -> Self {
+        Self {
+            left: Vec::new(),
+            right: vec![
+                WindowButton::Minimize,
+                WindowButton::Maximize,
+                WindowButton::Close,
+            ],
+            native: false,
+            snap_layouts: cfg!(windows),
+        }
+    }
+}
+
+impl ControlsLayout {
+    /// Parses a GTK decoration layout like `appmenu:minimize,maximize,close`.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn from_decoration_layout(layout: &str) -> Self {
+        let buttons = |side: &str| {
+            side.split(',')
+                .filter_map(|name| match name.trim() {
+                    "minimize" => Some(WindowButton::Minimize),
+                    "maximize" => Some(WindowButton::Maximize),
+                    "close" => Some(WindowButton::Close),
+                    // appmenu, icon, menu and spacer have no titlebar button here
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let (left, right) = layout.split_once(':').unwrap_or(("", layout));
+        Self {
+            left: buttons(left),
+            right: buttons(right),
+            ..Self::default()
+        }
+    }
+}
+
+/// Button order of the desktop: KWin's on KDE Plasma, GTK's everywhere else
+/// (which follows GNOME's `button-layout`).
+#[cfg(target_os = "linux")]
+fn desktop_layout() -> Option<ControlsLayout> {
+    use gtk::prelude::GtkSettingsExt;
+
+    let is_kde = std::env::var("XDG_CURRENT_DESKTOP").is_ok_and(|desktop| desktop.contains("KDE"));
+    let layout = if is_kde {
+        kwin_decoration_layout()
+    } else {
+        None
+    };
+
+    layout
+        .or_else(|| {
+            gtk::Settings::default()?
+                .gtk_decoration_layout()
+                .map(|layout| layout.to_string())
+        })
+        .map(|layout| ControlsLayout::from_decoration_layout(&layout))
+}
+
+/// Reads `ButtonsOnLeft`/`ButtonsOnRight` from `kwinrc` as a GTK decoration layout.
+#[cfg(target_os = "linux")]
+fn kwin_decoration_layout() -> Option<String> {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(std::path::PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(".config"))
+        })?;
+    let raw = std::fs::read_to_string(config.join("kwinrc")).ok()?;
+    Some(kwin_to_decoration_layout(&raw))
+}
+
+/// Turns the button letters of a `kwinrc` into a GTK decoration layout.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn kwin_to_decoration_layout(raw: &str) -> String {
+    // KWin's defaults
+    let mut left = "MS".to_string();
+    let mut right = "HIAX".to_string();
+
+    let mut in_section = false;
+    for line in raw.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_section = line == "[org.kde.kdecoration2]";
+        } else if in_section {
+            match line.split_once('=') {
+                Some(("ButtonsOnLeft", value)) => left = value.to_string(),
+                Some(("ButtonsOnRight", value)) => right = value.to_string(),
+                _ => {}
+            }
+        }
+    }
+
+    let names = |buttons: &str| {
+        buttons
+            .chars()
+            .filter_map(|button| match button {
+                'I' => Some("minimize"),
+                'A' => Some("maximize"),
+                'X' => Some("close"),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+
+    format!("{}:{}", names(&left), names(&right))
+}
+
+/// Layout of the window buttons on this platform.
+pub fn layout() -> ControlsLayout {
+    #[cfg(target_os = "macos")]
+    {
+        ControlsLayout {
+            left: Vec::new(),
+            right: Vec::new(),
+            native: true,
+            snap_layouts: false,
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        desktop_layout().unwrap_or_default()
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    {
+        ControlsLayout::default()
+    }
+}
+
+/// Handles the items of the window menu, registered once for the whole app.
+pub fn on_menu_event<R: Runtime>(app: &AppHandle<R>, event: MenuEvent) {
+    let mut parts = event.id().as_ref().splitn(3, ':');
+    let (Some(MENU_ID_PREFIX), Some(action), Some(label)) =
+        (parts.next(), parts.next(), parts.next())
+    else {
+        return;
+    };
+    let Some(window) = app.get_window(label) else {
+        return;
+    };
+
+    let _ = match action {
+        "restore" => window.unmaximize(),
+        "minimize" => window.minimize(),
+        "maximize" => window.maximize(),
+        "close" => window.close(),
+        _ => Ok(()),
+    };
+}
+
+/// Where the custom maximize button is, in physical pixels of the client area.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct ButtonRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// Windows 11 opens the snap layouts over a window that answers
+/// `WM_NCHITTEST` with `HTMAXBUTTON`, which the webview never does. So an
+/// invisible child window lies over the custom maximize button and answers
+/// for it, like the drag bar of Windows Terminal. The page only sees the
+/// pointer through the `maximize-button-state` event then.
+#[cfg(windows)]
+mod snap_layouts {
+    use std::cell::{Cell, RefCell};
+    use std::collections::HashMap;
+    use tauri::{Emitter, Runtime, Window};
+    use windows_sys::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{
+        TrackMouseEvent, TME_LEAVE, TME_NONCLIENT, TRACKMOUSEEVENT,
+    };
+    use windows_sys::Win32::UI::Shell::{DefSubclassProc, RemoveWindowSubclass, SetWindowSubclass};
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        CreateWindowExW, GetAncestor, IsWindow, IsZoomed, SetLayeredWindowAttributes, SetWindowPos,
+        ShowWindow, GA_ROOT, HTMAXBUTTON, HWND_TOP, LWA_ALPHA, SWP_HIDEWINDOW, SWP_NOACTIVATE,
+        SWP_SHOWWINDOW, SW_MAXIMIZE, SW_RESTORE, WM_NCDESTROY, WM_NCHITTEST, WM_NCLBUTTONDOWN,
+        WM_NCLBUTTONUP, WM_NCMOUSELEAVE, WM_NCMOUSEMOVE, WS_CHILD, WS_EX_LAYERED,
+        WS_EX_NOREDIRECTIONBITMAP,
+    };
+
+    use super::ButtonRect;
+
+    const SUBCLASS_ID: usize = 1;
+
+    /// State of one button window, owned by its subclass.
+    struct Button {
+        /// Sends `maximize-button-state` to the page.
+        report: Box<dyn Fn(&str)>,
+        hovered: Cell<bool>,
+        pressed: Cell<bool>,
+    }
+
+    impl Button {
+        fn set_state(&self, hovered: bool, pressed: bool) {
+            if (hovered, pressed) == (self.hovered.get(), self.pressed.get()) {
+                return;
+            }
+            self.hovered.set(hovered);
+            self.pressed.set(pressed);
+            (self.report)(match (hovered, pressed) {
+                (_, true) => "pressed",
+                (true, false) => "hover",
+                (false, false) => "none",
+            });
+        }
+    }
+
+    thread_local! {
+        /// Button windows by the label of their window. Only touched on the
+        /// main thread, where the windows live.
+        static BUTTONS: RefCell<HashMap<String, HWND>> = RefCell::new(HashMap::new());
+    }
+
+    unsafe extern "system" fn button_proc(
+        hwnd: HWND,
+        message: u32,
+        wparam: WPARAM,
+        lparam: LPARAM,
+        _id: usize,
+        data: usize,
+    ) -> LRESULT {
+        // SAFETY: `data` is the `Button` leaked in `place`, freed on WM_NCDESTROY
+        let button = &*(data as *const Button);
+
+        match message {
+            WM_NCHITTEST => HTMAXBUTTON as LRESULT,
+            WM_NCMOUSEMOVE => {
+                if !button.hovered.get() {
+                    let mut track = TRACKMOUSEEVENT {
+                        cbSize: std::mem::size_of::<TRACKMOUSEEVENT>() as u32,
+                        dwFlags: TME_LEAVE | TME_NONCLIENT,
+                        hwndTrack: hwnd,
+                        dwHoverTime: 0,
+                    };
+                    TrackMouseEvent(&mut track);
+                }
+                button.set_state(true, button.pressed.get());
+                0
+            }
+            WM_NCMOUSELEAVE => {
+                button.set_state(false, false);
+                0
+            }
+            // Handled here, DefWindowProc would draw a classic button
+            WM_NCLBUTTONDOWN => {
+                button.set_state(true, true);
+                0
+            }
+            WM_NCLBUTTONUP => {
+                if button.pressed.get() {
+                    let root = GetAncestor(hwnd, GA_ROOT);
+                    let command = if IsZoomed(root) != 0 {
+                        SW_RESTORE
+                    } else {
+                        SW_MAXIMIZE
+                    };
+                    ShowWindow(root, command);
+                }
+                button.set_state(true, false);
+                0
+            }
+            WM_NCDESTROY => {
+                RemoveWindowSubclass(hwnd, Some(button_proc), SUBCLASS_ID);
+                drop(Box::from_raw(data as *mut Button));
+                DefSubclassProc(hwnd, message, wparam, lparam)
+            }
+            _ => DefSubclassProc(hwnd, message, wparam, lparam),
+        }
+    }
+
+    /// Creates the button window of `window`, on the main thread.
+    fn create<R: Runtime>(window: &Window<R>) -> Result<HWND, String> {
+        let parent = window.hwnd().map_err(|e| e.to_string())?.0;
+        let class: Vec<u16> = "STATIC\0".encode_utf16().collect();
+
+        // SAFETY: `parent` is the live window of this thread, `class` is a
+        // NUL terminated system class
+        let hwnd = unsafe {
+            CreateWindowExW(
+                WS_EX_LAYERED | WS_EX_NOREDIRECTIONBITMAP,
+                class.as_ptr(),
+                std::ptr::null(),
+                WS_CHILD,
+                0,
+                0,
+                0,
+                0,
+                parent,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null(),
+            )
+        };
+        if hwnd.is_null() {
+            return Err("Failed to create the maximize button window".into());
+        }
+        // A layered window stays hidden, and gets no WM_NCHITTEST, until its
+        // attributes are set. Without a redirection bitmap it still draws nothing.
+        // SAFETY: `hwnd` was just created on this thread
+        unsafe {
+            SetLayeredWindowAttributes(hwnd, 0, 255, LWA_ALPHA);
+        }
+
+        let app = window.clone();
+        let label = window.label().to_string();
+        let button = Box::new(Button {
+            report: Box::new(move |state| {
+                let _ = app.emit_to(label.as_str(), "maximize-button-state", state);
+            }),
+            hovered: Cell::new(false),
+            pressed: Cell::new(false),
+        });
+        // SAFETY: the subclass owns `button` until WM_NCDESTROY
+        unsafe {
+            SetWindowSubclass(
+                hwnd,
+                Some(button_proc),
+                SUBCLASS_ID,
+                Box::into_raw(button) as usize,
+            );
+        }
+        Ok(hwnd)
+    }
+
+    /// Moves the button window of `window` over `rect`, or hides it.
+    pub fn place<R: Runtime>(window: &Window<R>, rect: Option<ButtonRect>) -> Result<(), String> {
+        let label = window.label().to_string();
+        // SAFETY: windows are only checked and moved on the main thread
+        let existing = BUTTONS
+            .with(|buttons| buttons.borrow().get(&label).copied())
+            .filter(|hwnd| unsafe { IsWindow(*hwnd) } != 0);
+
+        let hwnd = match (existing, rect) {
+            (Some(hwnd), _) => hwnd,
+            (None, None) => return Ok(()),
+            (None, Some(_)) => {
+                let hwnd = create(window)?;
+                BUTTONS.with(|buttons| buttons.borrow_mut().insert(label, hwnd));
+                hwnd
+            }
+        };
+
+        // Above the webview, which is created first
+        // SAFETY: `hwnd` is a live window of this thread
+        unsafe {
+            match rect {
+                Some(rect) => SetWindowPos(
+                    hwnd,
+                    HWND_TOP,
+                    rect.x,
+                    rect.y,
+                    rect.width,
+                    rect.height,
+                    SWP_NOACTIVATE | SWP_SHOWWINDOW,
+                ),
+                None => SetWindowPos(hwnd, HWND_TOP, 0, 0, 0, 0, SWP_NOACTIVATE | SWP_HIDEWINDOW),
+            };
+        }
+        Ok(())
+    }
+}
+
+// =========================================================================
+//                               COMMANDS
+// =========================================================================
+
+#[tauri::command]
+pub fn window_controls_layout() -> ControlsLayout {
+    layout()
+}
+
+/// Shows the window menu at the pointer, like right-clicking a native titlebar.
+#[tauri::command]
+pub fn show_window_menu<R: Runtime>(window: Window<R>) -> Result<(), String> {
+    let maximized = window.is_maximized().unwrap_or(false);
+    let item = |action: &str, text: &str, enabled: bool| {
+        MenuItem::with_id(
+            &window,
+            format!("{}:{}:{}", MENU_ID_PREFIX, action, window.label()),
+            text,
+            enabled,
+            None::<&str>,
+        )
+    };
+
+    let restore = item("restore", "Restore", maximized).map_err(|e| e.to_string())?;
+    let minimize = item("minimize", "Minimize", true).map_err(|e| e.to_string())?;
+    let maximize = item("maximize", "Maximize", !maximized).map_err(|e| e.to_string())?;
+    let separator = PredefinedMenuItem::separator(&window).map_err(|e| e.to_string())?;
+    let close = item("close", "Close", true).map_err(|e| e.to_string())?;
+
+    let items: [&dyn IsMenuItem<R>; 5] = [&restore, &minimize, &maximize, &separator, &close];
+    let menu = Menu::with_items(&window, &items).map_err(|e| e.to_string())?;
+
+    window.popup_menu(&menu).map_err(|e| e.to_string())
+}
+
+/// Lays the snap layouts hit area over the custom maximize button at `rect`,
+/// or hides it when there is no button. Windows only.
+#[tauri::command]
+pub fn place_maximize_button<R: Runtime>(
+    window: Window<R>,
+    rect: Option<ButtonRect>,
+) -> Result<(), String> {
+    #[cfg(windows)]
+    {
+        snap_layouts::place(&window, rect)
+    }
+
+    #[cfg(not(windows))]
+    {
+        let _ = (window, rect);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use WindowButton::{Close, Maximize, Minimize};
+
+    fn sides(layout: &str) -> (Vec<WindowButton>, Vec<WindowButton>) {
+        let layout = ControlsLayout::from_decoration_layout(layout);
+        (layout.left, layout.right)
+    }
+
+    #[test]
+    fn decoration_layout_splits_the_sides() {
+        assert_eq!(
+            sides("close,minimize:maximize"),
+            (vec![Close, Minimize], vec![Maximize])
+        );
+        assert_eq!(sides(":close"), (vec![], vec![Close]));
+        assert_eq!(sides("close:"), (vec![Close], vec![]));
+    }
+
+    #[test]
+    fn decoration_layout_without_a_colon_is_the_right_side() {
+        assert_eq!(
+            sides("minimize,maximize,close"),
+            (vec![], vec![Minimize, Maximize, Close])
+        );
+    }
+
+    #[test]
+    fn decoration_layout_skips_other_entries() {
+        assert_eq!(
+            sides("appmenu,icon:spacer,minimize, close,menu"),
+            (vec![], vec![Minimize, Close])
+        );
+        assert_eq!(sides("icon:appmenu"), (vec![], vec![]));
+    }
+
+    #[test]
+    fn kwin_layout_uses_the_defaults_without_its_section() {
+        assert_eq!(
+            kwin_to_decoration_layout("[Other]\nButtonsOnLeft=X\n"),
+            ":minimize,maximize,close"
+        );
+    }
+
+    #[test]
+    fn kwin_layout_maps_the_button_letters() {
+        let raw = "[org.kde.kdecoration2]\nButtonsOnLeft=XIA\nButtonsOnRight=NSHM\n[Other]\nButtonsOnRight=X\n";
+        assert_eq!(kwin_to_decoration_layout(raw), "close,minimize,maximize:");
+    }
+}
@@ -0,0 +1,46 @@
+use tauri::{plugin::TauriPlugin, AppHandle, Manager, Runtime};
+use tauri_plugin_window_state::DEFAULT_FILENAME;
+
+use crate::safe_mode;
+
+/// Left in the app config dir to reset the window states on the next launch.
+/// The window-state plugin saves them again on exit, so they can't simply be
+/// deleted while the app is running.
+const RESET_MARKER_FILE: &str = ".reset-window-state";
+
+/// Deletes the saved window states when `force` is set or a reset was asked
+/// for in the settings. Must be registered before the window-state plugin,
+/// which reads them once when it is set up.
+pub fn reset_plugin<R: Runtime>(force: bool) -> TauriPlugin<R> {
+    tauri::plugin::Builder::new("reset-window-state")
+        .setup(move |app, _api| {
+            let dir = app.path().app_config_dir()?;
+            let marker = dir.join(RESET_MARKER_FILE);
+            if !force && !marker.exists() {
+                return Ok(());
+            }
+            let _ = std::fs::remove_file(&marker);
+
+            match std::fs::remove_file(dir.join(DEFAULT_FILENAME)) {
+                Ok(()) => log::info!("Window state reset"),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => log::error!("Failed to reset window state: {}", e),
+            }
+            Ok(())
+        })
+        .build()
+}
+
+// =========================================================================
+//                               COMMANDS
+// =========================================================================
+
+/// Forgets the size and position of every window, restarting the app.
+#[tauri::command]
+pub fn reset_window_state<R: Runtime>(app: AppHandle<R>) -> Result<(), String> {
+    let dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    std::fs::write(dir.join(RESET_MARKER_FILE), "").map_err(|e| e.to_string())?;
+
+    safe_mode::restart(&app);
+}
@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, Runtime, Webview};
+
+use crate::injection;
+
+const ZOOM_FILE: &str = "window-zoom.json";
+
+const MIN_ZOOM: f64 = 0.5;
+const MAX_ZOOM: f64 = 3.0;
+const ZOOM_STEP: f64 = 0.1;
+
+const MIN_FONT_SCALE: f64 = 0.75;
+const MAX_FONT_SCALE: f64 = 2.0;
+
+/// Page zoom and text size of one window.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowZoom {
+    pub zoom: f64,
+    /// Applied to the font sizes of the injected stylesheet and Messenger's
+    /// root font size, leaving the rest of the layout alone.
+    pub font_scale: f64,
+}
+
+impl Default for WindowZoom {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            font_scale: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ZoomDirection {
+    In,
+    Out,
+    Reset,
+}
+
+/// Zoom of every window, by window label.
+pub struct ZoomStore {
+    windows: Mutex<HashMap<String, WindowZoom>>,
+    path: PathBuf,
+}
+
+impl ZoomStore {
+    pub fn load<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<Self> {
+        let path = app.path().app_config_dir()?.join(ZOOM_FILE);
+
+        let windows = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+
+        Ok(Self {
+            windows: Mutex::new(windows),
+            path,
+        })
+    }
+
+    pub fn get(&self, window: &str) -> WindowZoom {
+        self.windows
+            .lock()
+            .unwrap()
+            .get(window)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn set(&self, window: &str, zoom: WindowZoom) {
+        let mut windows = self.windows.lock().unwrap();
+        if zoom == WindowZoom::default() {
+            windows.remove(window);
+        } else {
+            windows.insert(window.to_string(), zoom);
+        }
+
+        if let Some(dir) = self.path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        match serde_json::to_string_pretty(&*windows) {
+            Ok(raw) => {
+                if let Err(e) = std::fs::write(&self.path, raw) {
+                    log::error!("Failed to save window zoom: {}", e);
+                }
+            }
+            Err(e) => log::error!("Failed to serialize window zoom: {}", e),
+        }
+    }
+}
+
+/// Zoom of the window labeled `window`, or the defaults before the store is loaded.
+pub fn current<R: Runtime>(app: &AppHandle<R>, window: &str) -> WindowZoom {
+    app.try_state::<ZoomStore>()
+        .map(|store| store.get(window))
+        .unwrap_or_default()
+}
+
+/// Applies the saved page zoom to a newly created webview.
+pub fn restore<R: Runtime>(webview: &Webview<R>) {
+    let zoom = current(webview.app_handle(), webview.window().label()).zoom;
+    if zoom != WindowZoom::default().zoom {
+        let _ = webview.set_zoom(zoom);
+    }
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+// =========================================================================
+//                               COMMANDS
+// =========================================================================
+
+#[tauri::command]
+pub fn get_window_zoom<R: Runtime>(
+    webview: Webview<R>,
+    store: tauri::State<'_, ZoomStore>,
+) -> WindowZoom {
+    store.get(webview.window().label())
+}
+
+/// Zooms every webview of the window `webview` belongs to.
+#[tauri::command]
+pub fn zoom<R: Runtime>(
+    webview: Webview<R>,
+    store: tauri::State<'_, ZoomStore>,
+    direction: ZoomDirection,
+) -> Result<f64, String> {
+    let window = webview.window();
+    let mut zoom = store.get(window.label());
+
+    zoom.zoom = match direction {
+        ZoomDirection::In => round(zoom.zoom + ZOOM_STEP).min(MAX_ZOOM),
+        ZoomDirection::Out => round(zoom.zoom - ZOOM_STEP).max(MIN_ZOOM),
+        ZoomDirection::Reset => WindowZoom::default().zoom,
+    };
+    store.set(window.label(), zoom);
+
+    for webview in window.webviews() {
+        webview.set_zoom(zoom.zoom).map_err(|e| e.to_string())?;
+    }
+
+    Ok(zoom.zoom)
+}
+
+#[tauri::command]
+pub fn set_font_scale<R: Runtime>(
+    app: AppHandle<R>,
+    webview: Webview<R>,
+    store: tauri::State<'_, ZoomStore>,
+    scale: f64,
+) {
+    let window = webview.window();
+    let mut zoom = store.get(window.label());
+    zoom.font_scale = round(scale.clamp(MIN_FONT_SCALE, MAX_FONT_SCALE));
+    store.set(window.label(), zoom);
+
+    injection::refresh_styles(&app);
+}